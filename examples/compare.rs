@@ -0,0 +1,87 @@
+//! Runs every way of drawing unique numbers once at a single thread count and ranks them
+//! from cheapest to most expensive per op: a shared `fetch_add`, a `fetch_add` every 16th
+//! op, thread-local sequencers, one sequencer behind a mutex and the CAS window of
+//! `LockFreeSequencer`. The full matrix, with repeats and reports, is the main binary.
+//!
+//! `cargo run --release --example compare [threads] [ops per thread]`
+
+use std::{
+    sync::{atomic::AtomicUsize, Arc},
+    time::{Duration, Instant},
+};
+
+use rust_benchmark_atomic::bench;
+use rust_benchmark_atomic::executor::{run_together, Spawn};
+use rust_benchmark_atomic::sequence::{LockFreeSequencer, SharedSequencer, SEQUENCER};
+
+const SKIP: usize = 16;
+const STEP: u64 = 128;
+
+/// Runs the timing loop `body(ops)` on each of `threads` fresh threads, started together
+/// like the benchmarks of the main binary, and returns the mean ns per op.
+fn measure<F>(threads: usize, ops: usize, body: F) -> f64
+where
+    F: Fn(usize) + Clone + Send + 'static,
+{
+    let total: Duration = run_together(&Spawn, threads, move |_| {
+        let body = body.clone();
+        move || {
+            let now = Instant::now();
+            body(ops);
+            now.elapsed()
+        }
+    })
+    .into_iter()
+    .sum();
+    total.as_nanos() as f64 / (threads * ops) as f64
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).map(|arg| arg.parse().unwrap());
+    let threads = args.next().unwrap_or(16);
+    let ops = args.next().unwrap_or(1_000_000);
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let shared = Arc::new(SharedSequencer::new(STEP, STEP * 16));
+    let lock_free = Arc::new(LockFreeSequencer::new(STEP));
+
+    let mut results = [
+        ("atomic", {
+            let counter = counter.clone();
+            measure(threads, ops, move |ops| bench::atomic(ops, &counter, 0))
+        }),
+        ("atomic skip", {
+            let counter = counter.clone();
+            measure(threads, ops, move |ops| {
+                bench::atomic_skip(ops, &counter, SKIP, 0)
+            })
+        }),
+        (
+            "sequencer",
+            measure(threads, ops, |ops| bench::sequencer(ops, &SEQUENCER, 0)),
+        ),
+        (
+            "mutex",
+            measure(threads, ops, move |ops| bench::shared_mutex(ops, &shared)),
+        ),
+        (
+            "lock-free",
+            measure(threads, ops, move |ops| {
+                bench::shared_lock_free(ops, &lock_free)
+            }),
+        ),
+    ];
+    results.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let cheapest = results[0].1;
+    println!("{} threads x {} ops", threads, ops);
+    for (rank, (name, ns)) in results.iter().enumerate() {
+        println!(
+            "{}. {:12} {:8.2}ns/op {:6.1}x",
+            rank + 1,
+            name,
+            ns,
+            ns / cheapest
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Simulates a write-ahead log: writer threads tag records with sequence numbers from the
+//! thread-local `SEQUENCER`, then a merge step checks that no record number was handed out
+//! twice and that every writer saw its own numbers strictly increasing.
+//!
+//! `cargo run --release --example wal [writers] [records per writer]`
+
+use std::time::Instant;
+
+use itertools::Itertools;
+use rust_benchmark_atomic::sequence::{Sequence, SEQUENCER};
+
+struct Record {
+    seq: Sequence,
+    writer: usize,
+    payload: u64,
+}
+
+fn write(writer: usize, records: usize) -> Vec<Record> {
+    (0..records)
+        .map(|i| Record {
+            seq: SEQUENCER.with(|s| s.borrow_mut().inc()),
+            writer,
+            payload: i as u64,
+        })
+        .collect()
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).map(|arg| arg.parse().unwrap());
+    let writers: usize = args.next().unwrap_or(8);
+    let records: usize = args.next().unwrap_or(1_000_000);
+
+    let now = Instant::now();
+    let logs = (0..writers)
+        .map(|writer| std::thread::spawn(move || write(writer, records)))
+        .collect_vec()
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect_vec();
+    let elapsed = now.elapsed();
+
+    // Sequencers only promise order within a thread; across threads windows interleave.
+    for log in &logs {
+        assert!(
+            log.iter().tuple_windows().all(|(a, b)| a.seq < b.seq),
+            "writer {} saw non-increasing sequences",
+            log[0].writer
+        );
+    }
+
+    let mut merged = logs.into_iter().flatten().collect_vec();
+    merged.sort_unstable_by_key(|record| record.seq);
+    if let Some((a, b)) = merged.iter().tuple_windows().find(|(a, b)| a.seq == b.seq) {
+        panic!(
+            "record {} written by writers {} (payload {}) and {} (payload {})",
+            a.seq, a.writer, a.payload, b.writer, b.payload
+        );
+    }
+
+    let total = merged.len();
+    let span = merged[total - 1].seq - merged[0].seq + 1;
+    println!(
+        "{} writers x {} records in {:?}: {:.2} Mrecords/s",
+        writers,
+        records,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64() / 1e6
+    );
+    println!(
+        "no duplicate record numbers; {} numbers spanned, {} left unused in windows",
+        span,
+        span - total as Sequence
+    );
+}
@@ -0,0 +1,88 @@
+//! Drives one `Sequencer` through an arbitrary series of operations and checks its
+//! invariants after each of them.
+//!
+//! Run from the repository root with a nightly toolchain:
+//!
+//! ```sh
+//! cargo install cargo-fuzz
+//! cargo +nightly fuzz run sequencer
+//! ```
+//!
+//! Input layout: the initial global head (8 bytes LE), `step` and `lag` (2 bytes LE each,
+//! `step` clamped to at least 1, a `lag` of 0 meaning `Sequence::MAX`), then one opcode
+//! byte plus a 2-byte LE argument per operation.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_benchmark_atomic::sequence::{Sequence, Sequencer, SEQUENCE_GLOBAL};
+use std::sync::atomic::Ordering;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((head, rest)) = data.split_first_chunk::<12>() else {
+        return;
+    };
+    let global = Sequence::from_le_bytes(head[..8].try_into().unwrap());
+    let step = u16::from_le_bytes([head[8], head[9]]).max(1) as Sequence;
+    let lag = match u16::from_le_bytes([head[10], head[11]]) {
+        0 => Sequence::MAX,
+        lag => lag as Sequence,
+    };
+    SEQUENCE_GLOBAL.store(global, Ordering::Relaxed);
+    let mut s = Sequencer::new(step, lag);
+
+    // Lowest sequence the next `inc` may return.
+    let mut floor = 0;
+    let mut last_head = global;
+    for op in rest.chunks_exact(3) {
+        let arg = u16::from_le_bytes([op[1], op[2]]) as Sequence;
+        // `skip` and `align_to` panic once the sequence space runs out, which is documented;
+        // only call them with room to spare. Each window they walk through costs at most
+        // `step` plus `arg` of global space.
+        let room = Sequence::MAX - SEQUENCE_GLOBAL.load(Ordering::Relaxed);
+        let roomy = (arg + 1).saturating_mul(step + arg) < room;
+        match op[0] % 6 {
+            0 => {
+                if let Some(seq) = s.try_inc() {
+                    assert!(seq >= floor, "inc went back: {} < {}", seq, floor);
+                    floor = seq + 1;
+                }
+            }
+            1 => {
+                if s.reserve(arg) {
+                    assert!(s.remaining() >= arg);
+                }
+            }
+            2 if roomy => {
+                s.skip(arg);
+                floor = s.local();
+            }
+            3 if roomy => {
+                s.align_to(arg.max(1));
+                assert!(s.local().is_multiple_of(arg.max(1)));
+                floor = s.local();
+            }
+            4 => {
+                if let Some(seq) = s.dec() {
+                    floor = seq;
+                }
+            }
+            5 => {
+                let before = s.local();
+                if let Some(child) = s.fork_range(arg) {
+                    assert_eq!(child.local_window(), before..before + arg);
+                    assert!(child.local_window().start >= floor);
+                    floor = s.local();
+                }
+            }
+            _ => {}
+        }
+        // Windows that would pass `Sequence::MAX` are refused, so the global never wraps.
+        let head = SEQUENCE_GLOBAL.load(Ordering::Relaxed);
+        assert!(head >= last_head, "global went back: {} < {}", head, last_head);
+        last_head = head;
+
+        let window = s.local_window();
+        assert!(window.start <= window.end, "local beyond target: {:?}", window);
+        assert_eq!(s.remaining(), window.end - window.start);
+    }
+});
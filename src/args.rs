@@ -0,0 +1,357 @@
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "config")]
+use crate::config::BenchConfig;
+#[cfg(feature = "numa")]
+use crate::numa::NumaPolicy;
+use crate::rng;
+use crate::timer::Timer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    /// One JSON object per line, each printed as soon as its result is in.
+    Jsonl,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+            Self::Jsonl => write!(f, "jsonl"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The sequencer parameters of one thread of `--mixed`, given as `step:lag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepLag {
+    pub step: u64,
+    pub lag: u64,
+}
+
+impl fmt::Display for StepLag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.step, self.lag)
+    }
+}
+
+impl FromStr for StepLag {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (step, lag) = s.split_once(':').ok_or(())?;
+        let (step, lag) = (step.parse().map_err(drop)?, lag.parse().map_err(drop)?);
+        if step == 0 || lag < step {
+            return Err(());
+        }
+        Ok(Self { step, lag })
+    }
+}
+
+pub struct Args {
+    pub repeat: usize,
+    pub format: Format,
+    /// Indent `--format json` output; implies `--format json`.
+    pub json_pretty: bool,
+    /// Print nothing until the end, then only the seed and the summary (or a single table of
+    /// all results).
+    pub quiet: bool,
+    /// Run the cross-thread uniqueness check instead of the benchmarks.
+    pub stress: bool,
+    pub stress_loops: usize,
+    /// Check the sequencer against a plain atomic counter over the same work, then exit.
+    pub verify: bool,
+    /// Run the create/drop churn comparison of window reclamation instead of the benchmarks.
+    pub churn: bool,
+    /// Print a step x threads grid of sequencer costs instead of the benchmarks.
+    pub heatmap: bool,
+    /// Keep thread counts above `available_parallelism` in the matrix.
+    pub oversubscribe: bool,
+    /// Compare the thread-local, lock-free shared and sharded schemes at every thread count.
+    pub head_to_head: bool,
+    /// Sweep the number of global counter shards at a fixed thread count.
+    pub shards: bool,
+    /// Run the sequencer with thread `i` on the `i`th of these parameters, cycling, at every
+    /// thread count instead of the benchmarks.
+    pub mixed: Option<Vec<StepLag>>,
+    /// Time the sequencer with and without a thread churning the global counter, at every
+    /// thread count, instead of the benchmarks.
+    pub global_churn: bool,
+    /// Double the thread count of the named benchmark until throughput plateaus.
+    pub scan: Option<String>,
+    /// Thread counts to run instead of the default matrix.
+    pub threads: Option<Vec<usize>>,
+    pub loops: Option<usize>,
+    /// Pick the loops of every benchmark to fill a fixed time budget, so that the suite takes
+    /// about as long on any machine.
+    pub ci: bool,
+    /// Benchmarks to run, by name; all of them if `None`. `--only` may be repeated.
+    pub benchmarks: Option<Vec<String>>,
+    /// Run only the largest thread count of the matrix.
+    pub only_last: bool,
+    /// Untimed runs of each thread count before the measured ones.
+    pub warmup: usize,
+    /// Run benchmarks on long-lived worker threads instead of spawning fresh ones.
+    pub pool: bool,
+    /// Clock of the timing loops.
+    pub timer: Timer,
+    /// Print every benchmark name with a description, and exit.
+    pub list: bool,
+    /// Validate the arguments and print what would run, without running it.
+    pub dry_run: bool,
+    /// Units of synthetic work after every counter operation of `atomic`, `atomic skip` and
+    /// `sequencer`.
+    pub work: usize,
+    /// Seed of the randomized benchmarks; each thread derives its own from it.
+    pub seed: u64,
+    /// Report costs including the timing loop overhead instead of net of it.
+    pub raw: bool,
+    /// Write the per thread samples of this run to a file, for a later `--baseline`.
+    pub save_baseline: Option<String>,
+    /// Append the resolved plan and every result to this file as JSON lines.
+    pub log_file: Option<String>,
+    /// Compare against samples saved by `--save-baseline` and fail on regressions.
+    pub baseline: Option<String>,
+    /// Significance level below which a slowdown against the baseline is a regression.
+    pub p_value: f64,
+    #[cfg(feature = "numa")]
+    pub numa: Option<NumaPolicy>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            repeat: 1,
+            format: Format::Text,
+            json_pretty: false,
+            quiet: false,
+            stress: false,
+            stress_loops: 1_000_000,
+            churn: false,
+            verify: false,
+            heatmap: false,
+            oversubscribe: false,
+            shards: false,
+            head_to_head: false,
+            mixed: None,
+            global_churn: false,
+            scan: None,
+            threads: None,
+            loops: None,
+            ci: false,
+            benchmarks: None,
+            only_last: false,
+            warmup: 0,
+            pool: false,
+            timer: Timer::Instant,
+            list: false,
+            dry_run: false,
+            raw: false,
+            work: 0,
+            seed: rng::DEFAULT_SEED,
+            save_baseline: None,
+            log_file: None,
+            baseline: None,
+            p_value: 0.05,
+            #[cfg(feature = "numa")]
+            numa: None,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let args = args.into_iter().collect::<Vec<_>>();
+        let mut res = Self::default();
+        // The config file is the base layer, whatever its position among the flags.
+        if let Some(i) = args.iter().position(|arg| arg == "--config") {
+            res.load_config(args.get(i + 1))?;
+        }
+
+        let mut only = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    args.next();
+                }
+                "--repeat" => res.repeat = value(&arg, args.next())?,
+                "--format" => res.format = value(&arg, args.next())?,
+                "--json-pretty" => res.json_pretty = true,
+                "--quiet" => res.quiet = true,
+                "--stress" => res.stress = true,
+                "--churn" => res.churn = true,
+                "--verify" => res.verify = true,
+                "--heatmap" => res.heatmap = true,
+                "--oversubscribe" => res.oversubscribe = true,
+                "--shards" => res.shards = true,
+                "--head-to-head" => res.head_to_head = true,
+                "--global-churn" => res.global_churn = true,
+                "--pool" => res.pool = true,
+                "--list" => res.list = true,
+                "--dry-run" => res.dry_run = true,
+                "--raw" => res.raw = true,
+                "--ci" => res.ci = true,
+                #[cfg(feature = "tsc")]
+                "--timer" => res.timer = value(&arg, args.next())?,
+                #[cfg(not(feature = "tsc"))]
+                "--timer" => return Err("--timer requires the `tsc` feature".to_string()),
+                "--only" => only.push(value(&arg, args.next())?),
+                "--only-last" => res.only_last = true,
+                "--save-baseline" => res.save_baseline = Some(value(&arg, args.next())?),
+                "--log-file" => res.log_file = Some(value(&arg, args.next())?),
+                "--baseline" => res.baseline = Some(value(&arg, args.next())?),
+                "--p-value" => res.p_value = value(&arg, args.next())?,
+                "--seed" => res.seed = value(&arg, args.next())?,
+                "--work" => res.work = value(&arg, args.next())?,
+                "--mixed" => res.mixed = Some(list(&arg, args.next())?),
+                "--scan" => res.scan = Some(value(&arg, args.next())?),
+                #[cfg(feature = "numa")]
+                "--numa" => res.numa = Some(value(&arg, args.next())?),
+                #[cfg(not(feature = "numa"))]
+                "--numa" => return Err("--numa requires the `numa` feature".to_string()),
+                "--stress-loops" => res.stress_loops = value(&arg, args.next())?,
+                "--threads" => res.threads = Some(list(&arg, args.next())?),
+                "--loops" => res.loops = Some(value(&arg, args.next())?),
+                "--warmup" => res.warmup = value(&arg, args.next())?,
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+        if !only.is_empty() {
+            res.benchmarks = Some(only);
+        }
+        if res.json_pretty {
+            res.format = Format::Json;
+        }
+        if res.repeat == 0 {
+            return Err("--repeat must be at least 1".to_string());
+        }
+        if res.threads.as_ref().is_some_and(|t| t.contains(&0)) {
+            return Err("thread counts must be at least 1".to_string());
+        }
+        if !(res.p_value > 0.0 && res.p_value < 1.0) {
+            return Err("--p-value must be between 0 and 1".to_string());
+        }
+        if res.loops == Some(0) {
+            return Err("loops must be at least 1".to_string());
+        }
+        if res.stress_loops == 0 {
+            return Err("--stress-loops must be at least 1".to_string());
+        }
+        Ok(res)
+    }
+
+    #[cfg(feature = "config")]
+    fn load_config(&mut self, path: Option<&String>) -> Result<(), String> {
+        let path = path.ok_or("missing value for --config")?;
+        let config = BenchConfig::load(path)?;
+        self.threads = config.threads;
+        self.loops = config.loops;
+        self.benchmarks = config.benchmarks;
+        self.warmup = config.warmup.unwrap_or(self.warmup);
+        self.format = config.format.unwrap_or(self.format);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "config"))]
+    fn load_config(&mut self, _: Option<&String>) -> Result<(), String> {
+        Err("--config requires the `config` feature".to_string())
+    }
+}
+
+// A comma separated `value`.
+fn list<T: FromStr>(flag: &str, value: Option<String>) -> Result<Vec<T>, String> {
+    let value = value.ok_or_else(|| format!("missing value for {}", flag))?;
+    value
+        .split(',')
+        .map(|item| {
+            item.parse()
+                .map_err(|_| format!("invalid value for {}: {}", flag, item))
+        })
+        .collect()
+}
+
+fn value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for {}", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn matrix_flags() {
+        let args = parse("--threads 1,4,16 --loops 1000 --warmup 2").unwrap();
+        assert_eq!(args.threads, Some(vec![1, 4, 16]));
+        assert_eq!(args.loops, Some(1000));
+        assert_eq!(args.warmup, 2);
+        assert!(parse("--threads 1,0").is_err());
+        assert!(parse("--loops 0").is_err());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn flags_override_the_config_file() {
+        let path = std::env::temp_dir().join(format!("bench-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "threads = [2, 8]\nloops = 500\nwarmup = 1\nformat = \"json\"\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = parse(&format!("--config {}", path)).unwrap();
+        assert_eq!(args.threads, Some(vec![2, 8]));
+        assert_eq!(
+            (args.loops, args.warmup, args.format),
+            (Some(500), 1, Format::Json)
+        );
+
+        let args = parse(&format!(
+            "--threads 4 --config {} --loops 9 --warmup 0",
+            path
+        ))
+        .unwrap();
+        assert_eq!(args.threads, Some(vec![4]));
+        assert_eq!(
+            (args.loops, args.warmup, args.format),
+            (Some(9), 0, Format::Json)
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stress_loops_must_be_positive() {
+        assert_eq!(
+            parse("--stress --stress-loops 10").unwrap().stress_loops,
+            10
+        );
+        assert!(parse("--stress --stress-loops 0").is_err());
+    }
+}
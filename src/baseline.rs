@@ -0,0 +1,200 @@
+use std::{collections::BTreeMap, f64::consts::PI, fmt::Write, fs};
+
+use crate::report::BenchResult;
+
+/// Per thread ns per iter samples of a run, keyed by `(threads, name)` and merged over
+/// repeats.
+///
+/// Stored as one line per benchmark: threads, name and the space separated samples,
+/// separated by tabs.
+pub type Baseline = BTreeMap<(usize, String), Vec<f64>>;
+
+pub fn collect(results: &[BenchResult]) -> Baseline {
+    let mut baseline = Baseline::new();
+    for result in results {
+        baseline
+            .entry((result.threads, result.name.clone()))
+            .or_default()
+            .extend(&result.samples);
+    }
+    baseline
+}
+
+pub fn save(path: &str, results: &[BenchResult]) -> Result<(), String> {
+    let mut out = String::new();
+    for ((threads, name), samples) in collect(results) {
+        let samples = samples.iter().map(f64::to_string).collect::<Vec<_>>();
+        writeln!(out, "{}\t{}\t{}", threads, name, samples.join(" ")).unwrap();
+    }
+    fs::write(path, out).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn load(path: &str) -> Result<Baseline, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut baseline = Baseline::new();
+    for (i, line) in text.lines().enumerate() {
+        let invalid = || format!("{}:{}: invalid baseline line", path, i + 1);
+        let mut fields = line.split('\t');
+        let (Some(threads), Some(name), Some(samples), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid());
+        };
+        let threads = threads.parse().map_err(|_| invalid())?;
+        let samples = samples
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| invalid())?;
+        baseline.insert((threads, name.to_string()), samples);
+    }
+    Ok(baseline)
+}
+
+/// Prints every benchmark of `current` that is slower than in `baseline` with a one-sided
+/// p-value below `alpha`, and returns how many there were. Benchmarks missing from either
+/// side or with fewer than two samples are skipped.
+pub fn compare(baseline: &Baseline, current: &Baseline, alpha: f64) -> usize {
+    let mut regressions = 0;
+    for (key, after) in current {
+        let Some(before) = baseline.get(key) else {
+            continue;
+        };
+        let Some(p) = welch_slower(before, after) else {
+            continue;
+        };
+        if p < alpha {
+            regressions += 1;
+            println!(
+                "regression: {:20} {} threads: {:.2}ns -> {:.2}ns (p = {:.4})",
+                key.1,
+                key.0,
+                mean(before),
+                mean(after),
+                p
+            );
+        }
+    }
+    regressions
+}
+
+/// One-sided Welch's t-test: the p-value of `after` being no slower than `before`.
+/// `None` if either side has fewer than two samples.
+pub fn welch_slower(before: &[f64], after: &[f64]) -> Option<f64> {
+    if before.len() < 2 || after.len() < 2 {
+        return None;
+    }
+    let (nb, na) = (before.len() as f64, after.len() as f64);
+    let (vb, va) = (variance(before) / nb, variance(after) / na);
+    let diff = mean(after) - mean(before);
+    let se2 = vb + va;
+    if se2 == 0.0 {
+        return Some(if diff > 0.0 { 0.0 } else { 1.0 });
+    }
+    let t = diff / se2.sqrt();
+    // Welch–Satterthwaite degrees of freedom.
+    let df = se2 * se2 / (vb * vb / (nb - 1.0) + va * va / (na - 1.0));
+    // P(T > |t|) of the Student t distribution.
+    let tail = 0.5 * inc_beta(df / 2.0, 0.5, df / (df + t * t));
+    Some(if t > 0.0 { tail } else { 1.0 - tail })
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+fn variance(xs: &[f64]) -> f64 {
+    let m = mean(xs);
+    xs.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / (xs.len() - 1) as f64
+}
+
+// Regularized incomplete beta function, by continued fraction (Numerical Recipes 6.4).
+fn inc_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_cf(a, b, x) / a
+    } else {
+        1.0 - front * beta_cf(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_cf(a: f64, b: f64, x: f64) -> f64 {
+    const EPS: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+    let nonzero = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / nonzero(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / nonzero(1.0 + even * d);
+        c = nonzero(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / nonzero(1.0 + odd * d);
+        c = nonzero(1.0 + odd / c);
+        h *= d * c;
+        if (d * c - 1.0).abs() < EPS {
+            break;
+        }
+    }
+    h
+}
+
+// Lanczos approximation with g = 7.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn welch_tells_shifted_samples_apart() {
+        let before = [10.0, 11.0, 9.0, 10.0, 10.5, 9.5];
+        let slower = before.map(|ns| ns + 5.0);
+        assert!(welch_slower(&before, &slower).unwrap() < 1e-6);
+        // Faster is no regression.
+        assert!(welch_slower(&slower, &before).unwrap() > 0.999);
+
+        // The same distribution, reordered.
+        let p = welch_slower(&before, &[9.5, 10.0, 11.0, 10.5, 9.0, 10.0]).unwrap();
+        assert!((p - 0.5).abs() < 1e-9, "p = {}", p);
+
+        // t = 1 at 8 degrees of freedom.
+        let p = welch_slower(&[1.0, 2.0, 3.0, 4.0, 5.0], &[2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        assert!((p - 0.1733).abs() < 1e-3, "p = {}", p);
+
+        assert_eq!(welch_slower(&[1.0], &[2.0, 3.0]), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    cell::RefCell,
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::LocalKey,
+};
+
+use crate::sequence::{LockFreeSequencer, Sequencer, SharedSequencer};
+
+// The timing loops of the main binary's benchmarks, shared with `examples/compare.rs`.
+// Callers time them. Every result passes through `black_box`, so that the optimizer can
+// neither drop the loop body nor fold the loop into a closed form.
+
+/// `units` rounds of a dependent multiply-add, standing in for the work an application does
+/// between two counter operations. Returns how many rounds ran.
+#[inline(always)]
+pub fn work(units: usize) -> usize {
+    let mut x = 1u64;
+    for i in 0..units {
+        x = black_box(
+            x.wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(i as u64),
+        );
+    }
+    units
+}
+
+/// `loops` relaxed `fetch_add`s on one shared counter, with `units` of `work` after each.
+#[inline]
+pub fn atomic(loops: usize, counter: &AtomicUsize, units: usize) {
+    for _ in 0..loops {
+        black_box(counter.fetch_add(1, Ordering::Relaxed));
+        work(units);
+    }
+}
+
+/// Like `atomic`, but only every `skip`th op adds, `skip` at once; the others load.
+#[inline]
+pub fn atomic_skip(loops: usize, counter: &AtomicUsize, skip: usize, units: usize) {
+    for i in 1..=loops {
+        if i.is_multiple_of(skip) {
+            black_box(counter.fetch_add(skip, Ordering::Relaxed));
+        } else {
+            black_box(counter.load(Ordering::Relaxed));
+        }
+        work(units);
+    }
+}
+
+/// `loops` incs of a thread-local sequencer, with `units` of `work` after each.
+#[inline]
+pub fn sequencer(loops: usize, sequencer: &'static LocalKey<RefCell<Sequencer>>, units: usize) {
+    for _ in 0..loops {
+        black_box(sequencer.with(|s| s.borrow_mut().inc()));
+        work(units);
+    }
+}
+
+/// `loops` incs of one sequencer behind a mutex.
+#[inline]
+pub fn shared_mutex(loops: usize, sequencer: &SharedSequencer) {
+    for _ in 0..loops {
+        black_box(sequencer.inc());
+    }
+}
+
+/// `loops` incs of one lock-free window shared between threads.
+#[inline]
+pub fn shared_lock_free(loops: usize, sequencer: &LockFreeSequencer) {
+    for _ in 0..loops {
+        black_box(sequencer.inc());
+    }
+}
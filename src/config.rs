@@ -0,0 +1,241 @@
+use std::{fs, iter::Peekable, str::Chars, str::FromStr};
+
+use crate::args::Format;
+
+/// A benchmark suite read from `--config`. Every field is optional; flags given on the
+/// command line take precedence.
+///
+/// Only a flat subset of TOML is understood: one `key = value` per line, where values are
+/// integers, basic strings or single-line arrays of those, plus `#` comments.
+///
+/// ```toml
+/// threads = [1, 4, 16]
+/// loops = 1_000_000
+/// benchmarks = ["atomic", "sequencer(128,16)"]
+/// warmup = 1
+/// format = "json"
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct BenchConfig {
+    pub threads: Option<Vec<usize>>,
+    pub loops: Option<usize>,
+    pub benchmarks: Option<Vec<String>>,
+    pub warmup: Option<usize>,
+    pub format: Option<Format>,
+}
+
+impl BenchConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        text.parse().map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+impl FromStr for BenchConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        for (i, line) in s.lines().enumerate() {
+            let err = |e: String| format!("line {}: {}", i + 1, e);
+            let mut parser = Parser(line.chars().peekable());
+            let Some(key) = parser.key().map_err(err)? else {
+                continue;
+            };
+            let value = parser.value().map_err(err)?;
+            parser.end().map_err(err)?;
+            match key.as_str() {
+                "threads" => config.threads = Some(value.ints().map_err(err)?),
+                "loops" => config.loops = Some(value.int().map_err(err)?),
+                "benchmarks" => config.benchmarks = Some(value.strs().map_err(err)?),
+                "warmup" => config.warmup = Some(value.int().map_err(err)?),
+                "format" => {
+                    let format = value.str().map_err(err)?;
+                    config.format = Some(
+                        format
+                            .parse()
+                            .map_err(|_| err(format!("unknown format `{}`", format)))?,
+                    );
+                }
+                _ => return Err(err(format!("unknown key `{}`", key))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+enum Value {
+    Int(usize),
+    Str(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn int(self) -> Result<usize, String> {
+        match self {
+            Self::Int(v) => Ok(v),
+            _ => Err("expected an integer".to_string()),
+        }
+    }
+
+    fn str(self) -> Result<String, String> {
+        match self {
+            Self::Str(v) => Ok(v),
+            _ => Err("expected a string".to_string()),
+        }
+    }
+
+    fn array(self) -> Result<Vec<Value>, String> {
+        match self {
+            Self::Array(v) => Ok(v),
+            _ => Err("expected an array".to_string()),
+        }
+    }
+
+    fn ints(self) -> Result<Vec<usize>, String> {
+        self.array()?.into_iter().map(Value::int).collect()
+    }
+
+    fn strs(self) -> Result<Vec<String>, String> {
+        self.array()?.into_iter().map(Value::str).collect()
+    }
+}
+
+struct Parser<'a>(Peekable<Chars<'a>>);
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while self.0.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_ws();
+        match self.0.next() {
+            Some(got) if got == c => Ok(()),
+            Some(got) => Err(format!("expected `{}`, found `{}`", c, got)),
+            None => Err(format!("expected `{}`", c)),
+        }
+    }
+
+    /// Returns `None` for blank and comment-only lines.
+    fn key(&mut self) -> Result<Option<String>, String> {
+        self.end().map(|_| None).or_else(|_| {
+            let mut key = String::new();
+            while let Some(c) = self
+                .0
+                .next_if(|&c| c.is_alphanumeric() || c == '_' || c == '-')
+            {
+                key.push(c);
+            }
+            if key.is_empty() {
+                return Err("expected a key".to_string());
+            }
+            self.expect('=')?;
+            Ok(Some(key))
+        })
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.0.peek() {
+            Some('"') => {
+                self.0.next();
+                let mut s = String::new();
+                loop {
+                    match self.0.next() {
+                        Some('"') => return Ok(Value::Str(s)),
+                        Some('\\') => return Err("escapes are not supported".to_string()),
+                        Some(c) => s.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+            }
+            Some('[') => {
+                self.0.next();
+                let mut items = vec![];
+                loop {
+                    self.skip_ws();
+                    if self.0.next_if_eq(&']').is_some() {
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_ws();
+                    if self.0.next_if_eq(&',').is_none() {
+                        self.expect(']')?;
+                        return Ok(Value::Array(items));
+                    }
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(c) = self.0.next_if(|&c| c.is_ascii_digit() || c == '_') {
+                    if c != '_' {
+                        digits.push(c);
+                    }
+                }
+                digits
+                    .parse()
+                    .map(Value::Int)
+                    .map_err(|_| format!("integer out of range: {}", digits))
+            }
+            Some(c) => Err(format!("unsupported value starting with `{}`", c)),
+            None => Err("expected a value".to_string()),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), String> {
+        self.skip_ws();
+        match self.0.peek() {
+            None | Some('#') => Ok(()),
+            Some(c) => Err(format!("unexpected `{}`", c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_suite() {
+        let config = "
+            # shared setup
+            threads = [1, 4, 16]
+            loops = 1_000_000
+            benchmarks = [\"atomic\", \"sequencer(128,16)\"]  # two of them
+            warmup = 1
+            format = \"json\"
+        "
+        .parse::<BenchConfig>()
+        .unwrap();
+        assert_eq!(
+            config,
+            BenchConfig {
+                threads: Some(vec![1, 4, 16]),
+                loops: Some(1_000_000),
+                benchmarks: Some(vec!["atomic".into(), "sequencer(128,16)".into()]),
+                warmup: Some(1),
+                format: Some(Format::Json),
+            }
+        );
+        assert_eq!("".parse::<BenchConfig>().unwrap(), BenchConfig::default());
+    }
+
+    #[test]
+    fn rejects_what_it_does_not_understand() {
+        for bad in [
+            "threads = 4",
+            "loops = \"many\"",
+            "format = \"yaml\"",
+            "color = \"red\"",
+            "loops = 1 2",
+            "benchmarks = [\"a\"",
+        ] {
+            assert!(bad.parse::<BenchConfig>().is_err(), "{}", bad);
+        }
+        assert_eq!(
+            "\n\nloops = x".parse::<BenchConfig>().unwrap_err(),
+            "line 3: unsupported value starting with `x`"
+        );
+    }
+}
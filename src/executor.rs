@@ -0,0 +1,97 @@
+use std::{
+    sync::{mpsc, Arc, Barrier, Mutex},
+    thread::JoinHandle,
+};
+
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Where benchmark threads come from.
+pub trait Executor: Sync {
+    /// Runs every job on its own thread, all of them concurrently, and returns once all
+    /// have finished. Panics if a job does.
+    fn execute(&self, jobs: Vec<Job>);
+}
+
+/// Runs thread `i` of `threads` on `executor` in two steps: `setup(i)`, then the body it
+/// returns, once every thread is done setting up. Holding them all at a barrier in between
+/// makes the bodies, i.e. the timing loops, overlap fully. Returns what the bodies returned,
+/// in the order they finished.
+pub fn run_together<S, B, T>(executor: &dyn Executor, threads: usize, setup: S) -> Vec<T>
+where
+    S: Fn(usize) -> B + Clone + Send + 'static,
+    B: FnOnce() -> T,
+    T: Send + 'static,
+{
+    let barrier = Arc::new(Barrier::new(threads));
+    let (tx, rx) = mpsc::channel();
+    let jobs = (0..threads)
+        .map(|i| {
+            let setup = setup.clone();
+            let barrier = barrier.clone();
+            let tx = tx.clone();
+            Box::new(move || {
+                let body = setup(i);
+                barrier.wait();
+                tx.send(body()).unwrap();
+            }) as Job
+        })
+        .collect();
+    drop(tx);
+    executor.execute(jobs);
+    rx.iter().collect()
+}
+
+/// Fresh threads for every run, so thread-locals always start out empty.
+pub struct Spawn;
+
+impl Executor for Spawn {
+    fn execute(&self, jobs: Vec<Job>) {
+        let handles = jobs.into_iter().map(std::thread::spawn).collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+}
+
+/// Long-lived workers, reused across runs: thread-local windows carry over from one run to
+/// the next, the way they do on an application thread pool.
+#[derive(Default)]
+pub struct Pool {
+    workers: Mutex<Vec<(mpsc::Sender<Job>, JoinHandle<()>)>>,
+}
+
+impl Executor for Pool {
+    fn execute(&self, jobs: Vec<Job>) {
+        let mut workers = self.workers.lock().unwrap();
+        // Jobs may wait on each other, so each one needs a worker of its own.
+        while workers.len() < jobs.len() {
+            let (tx, rx) = mpsc::channel::<Job>();
+            let handle = std::thread::spawn(move || rx.into_iter().for_each(|job| job()));
+            workers.push((tx, handle));
+        }
+
+        let n = jobs.len();
+        let (done_tx, done_rx) = mpsc::channel();
+        for (job, (tx, _)) in jobs.into_iter().zip(workers.iter()) {
+            let done_tx = done_tx.clone();
+            tx.send(Box::new(move || {
+                job();
+                done_tx.send(()).unwrap();
+            }))
+            .expect("pool worker died");
+        }
+        drop(done_tx);
+        // A panicking job drops its sender without reporting, which ends the wait early.
+        let finished = done_rx.iter().take(n).count();
+        assert_eq!(finished, n, "benchmark job panicked");
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        for (tx, handle) in self.workers.get_mut().unwrap().drain(..) {
+            drop(tx);
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+/// Just enough JSON to emit benchmark reports without pulling in a serializer.
+pub enum Json {
+    Bool(bool),
+    Int(u64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl From<bool> for Json {
+    fn from(v: bool) -> Self {
+        Self::Bool(v)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(v: u64) -> Self {
+        Self::Int(v)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(v: f64) -> Self {
+        Self::Float(v)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(v: &str) -> Self {
+        Self::Str(v.to_string())
+    }
+}
+
+impl Json {
+    pub fn object<'a, I>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, Json)>,
+    {
+        Self::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    /// Appends a field to an object.
+    pub fn push(&mut self, key: impl Into<String>, value: Json) {
+        match self {
+            Self::Object(fields) => fields.push((key.into(), value)),
+            _ => panic!("push on a non-object"),
+        }
+    }
+
+    pub fn render(&self, pretty: bool) -> String {
+        let mut out = String::new();
+        self.write(&mut out, pretty.then_some(0));
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: Option<usize>) {
+        match self {
+            Self::Bool(v) => write!(out, "{}", v).unwrap(),
+            Self::Int(v) => write!(out, "{}", v).unwrap(),
+            Self::Float(v) if v.is_finite() => write!(out, "{}", v).unwrap(),
+            Self::Float(_) => out.push_str("null"),
+            Self::Str(v) => write_str(out, v),
+            Self::Array(items) => write_seq(out, indent, '[', ']', items, |out, item, indent| {
+                item.write(out, indent)
+            }),
+            Self::Object(fields) => {
+                write_seq(out, indent, '{', '}', fields, |out, (k, v), indent| {
+                    write_str(out, k);
+                    out.push(':');
+                    if indent.is_some() {
+                        out.push(' ');
+                    }
+                    v.write(out, indent);
+                })
+            }
+        }
+    }
+}
+
+fn write_seq<T>(
+    out: &mut String,
+    indent: Option<usize>,
+    open: char,
+    close: char,
+    items: &[T],
+    mut f: impl FnMut(&mut String, &T, Option<usize>),
+) {
+    out.push(open);
+    let inner = indent.map(|i| i + 2);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        newline(out, inner);
+        f(out, item, inner);
+    }
+    if !items.is_empty() {
+        newline(out, indent);
+    }
+    out.push(close);
+}
+
+fn newline(out: &mut String, indent: Option<usize>) {
+    if let Some(indent) = indent {
+        out.push('\n');
+        out.extend(std::iter::repeat_n(' ', indent));
+    }
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,8 @@
+pub mod bench;
+pub mod executor;
+#[cfg(all(feature = "mmap", unix))]
+pub mod mmap;
+pub mod ring;
+pub mod sequence;
+pub mod signed;
+pub mod typed;
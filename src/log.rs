@@ -0,0 +1,110 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::json::Json;
+use crate::report::{to_json_line, BenchResult};
+
+/// A durable record of a run for `--log-file`, whatever goes to stdout: one JSON object per
+/// line, each with an `event` and a `unix_ms` timestamp. The file is appended to, so runs
+/// accumulate, and every line goes out in a single write as soon as it is known.
+pub struct RunLog {
+    path: String,
+    file: File,
+}
+
+impl RunLog {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self {
+            path: path.to_string(),
+            file,
+        })
+    }
+
+    /// The resolved configuration and the environment, ahead of any result.
+    pub fn plan(&mut self, config: Json) {
+        self.write(
+            "plan",
+            Json::object([("config", config), ("environment", environment())]),
+        );
+    }
+
+    pub fn result(&mut self, result: &BenchResult) {
+        self.write("result", to_json_line(result));
+    }
+
+    pub fn done(&mut self, results: usize) {
+        self.write(
+            "done",
+            Json::object([("results", Json::from(results as u64))]),
+        );
+    }
+
+    fn write(&mut self, event: &str, fields: Json) {
+        let unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_millis() as u64);
+        let mut line = Json::object([
+            ("event", Json::from(event)),
+            ("unix_ms", Json::from(unix_ms)),
+        ]);
+        if let Json::Object(fields) = fields {
+            fields.into_iter().for_each(|(k, v)| line.push(k, v));
+        }
+        let mut text = line.render(false);
+        text.push('\n');
+        // A lost audit line shouldn't take the run down with it.
+        if let Err(e) = self
+            .file
+            .write_all(text.as_bytes())
+            .and_then(|()| self.file.flush())
+        {
+            eprintln!("{}: {}", self.path, e);
+        }
+    }
+}
+
+fn environment() -> Json {
+    let cores = std::thread::available_parallelism().map_or(0, |n| n.get() as u64);
+    let features = [
+        ("stats", cfg!(feature = "stats")),
+        ("config", cfg!(feature = "config")),
+        ("numa", cfg!(feature = "numa")),
+        ("migration", cfg!(feature = "migration")),
+        ("tsc", cfg!(feature = "tsc")),
+        ("mmap", cfg!(feature = "mmap")),
+        ("metrics", cfg!(feature = "metrics")),
+    ];
+    Json::object([
+        ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+        ("os", Json::from(std::env::consts::OS)),
+        ("arch", Json::from(std::env::consts::ARCH)),
+        ("cores", Json::from(cores)),
+        (
+            "features",
+            Json::Array(
+                features
+                    .iter()
+                    .filter(|(_, on)| *on)
+                    .map(|(name, _)| Json::from(*name))
+                    .collect(),
+            ),
+        ),
+        (
+            "args",
+            Json::Array(
+                std::env::args()
+                    .skip(1)
+                    .map(|a| Json::from(a.as_str()))
+                    .collect(),
+            ),
+        ),
+    ])
+}
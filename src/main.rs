@@ -1,71 +1,140 @@
-#[allow(dead_code)]
-mod sequence;
-use sequence::*;
+mod args;
+mod baseline;
+#[cfg(feature = "config")]
+mod config;
+mod json;
+use json::Json;
+mod log;
+use log::RunLog;
+mod quantile;
+use quantile::QuantileEstimator;
+#[cfg(feature = "numa")]
+mod numa;
+mod report;
+mod rng;
+mod timer;
+use args::{Args, Format, StepLag};
+use report::*;
+use rng::XorShift;
+use rust_benchmark_atomic::bench;
+use rust_benchmark_atomic::executor::{run_together, Executor, Job, Pool, Spawn};
+use rust_benchmark_atomic::sequence::*;
+use timer::Timer;
 
 use std::{
     cell::RefCell,
+    collections::HashMap,
+    fmt::Write,
     hint::black_box,
+    ops::Deref,
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
+        atomic::{fence, AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
     },
+    thread::LocalKey,
     time::{Duration, Instant},
 };
 
 use itertools::Itertools;
 
+#[cfg(feature = "numa")]
+use numa::pin_current;
+
+#[cfg(not(feature = "numa"))]
+fn pin_current(_: usize) {}
+
+/// The CPU the calling thread runs on, where that can be asked.
+#[cfg(all(feature = "migration", target_os = "linux"))]
+fn current_cpu() -> Option<usize> {
+    // SAFETY: no preconditions; failure is reported as -1.
+    let cpu = unsafe { libc::sched_getcpu() };
+    usize::try_from(cpu).ok()
+}
+
+#[cfg(not(all(feature = "migration", target_os = "linux")))]
+fn current_cpu() -> Option<usize> {
+    None
+}
+
 thread_local! {
-    pub static SEQUENCER_64_8: RefCell<Sequencer> = RefCell::new(Sequencer::new(64, 64 * 8));
-    pub static SEQUENCER_64_16: RefCell<Sequencer> = RefCell::new(Sequencer::new(64, 64 * 16));
-    pub static SEQUENCER_64_32: RefCell<Sequencer> = RefCell::new(Sequencer::new(64, 64 * 32));
-    pub static SEQUENCER_128_8: RefCell<Sequencer> = RefCell::new(Sequencer::new(128, 128 * 8));
-    pub static SEQUENCER_128_16: RefCell<Sequencer> = RefCell::new(Sequencer::new(128, 128 * 16));
-    pub static SEQUENCER_128_32: RefCell<Sequencer> = RefCell::new(Sequencer::new(128, 128 * 32));
+    pub static SEQUENCER_64_8: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(64, 64 * 8)) };
+    pub static SEQUENCER_64_16: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(64, 64 * 16)) };
+    pub static SEQUENCER_64_32: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(64, 64 * 32)) };
+    pub static SEQUENCER_128_8: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(128, 128 * 8)) };
+    pub static SEQUENCER_128_16: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(128, 128 * 16)) };
+    pub static SEQUENCER_128_32: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(128, 128 * 32)) };
+    // Set up at the start of each run, for parameters without a static above.
+    pub static SEQUENCER_DYN: RefCell<Option<Sequencer>> = const { RefCell::new(None) };
 }
 
+// Every timing loop passes its per iter result through `black_box`: the optimizer may then
+// neither drop the loop body nor fold the whole loop into a closed form, which is how
+// `primitive` used to report 0ns. Atomic and sequencer results would mostly survive on
+// their own, as the operations are observable, but get the same treatment for uniformity.
 fn coarse(loops: usize) -> Duration {
-    let now = Instant::now();
+    let now = timer::start();
     for _ in 0..loops {
-        let _ = coarsetime::Instant::now();
+        black_box(coarsetime::Instant::now());
     }
     now.elapsed()
 }
 
+#[allow(clippy::explicit_counter_loop)]
 fn primitive(loops: usize) -> Duration {
     let mut cnt = 0usize;
-    let now = Instant::now();
+    let now = timer::start();
     for _ in 0..loops {
         cnt += 1;
-        let _ = cnt;
+        black_box(cnt);
     }
     now.elapsed()
 }
 
-fn atomic(loops: usize, atomic: Arc<AtomicUsize>) -> Duration {
-    let now = Instant::now();
+// Generic over how the counter is shared, so that `atomic static` can check that the `Arc`
+// of the other benchmarks doesn't show in their cost.
+fn atomic(loops: usize, atomic: impl Deref<Target = AtomicUsize>, units: usize) -> Duration {
+    let now = timer::start();
+    bench::atomic(loops, &atomic, units);
+    now.elapsed()
+}
+
+// Every thread only reads the shared counter, so its line stays shared in all caches and is
+// never invalidated: the floor under `atomic`, which pays for the invalidations of writes.
+fn atomic_load(loops: usize, atomic: Arc<AtomicUsize>) -> Duration {
+    let now = timer::start();
     for _ in 0..loops {
-        let _ = atomic.fetch_add(1, Ordering::Relaxed);
+        black_box(atomic.load(Ordering::Relaxed));
     }
     now.elapsed()
 }
 
-fn atomic_skip(loops: usize, atomic: Arc<AtomicUsize>, skip: usize) -> Duration {
-    let mut cnt = 0usize;
-    let now = Instant::now();
+// Two ways of publishing data gated by the counter, to compare against the bare `Relaxed`
+// increment of `atomic`: an `AcqRel` RMW, and a `Relaxed` one followed by a release fence.
+fn atomic_acq_rel(loops: usize, atomic: Arc<AtomicUsize>) -> Duration {
+    let now = timer::start();
     for _ in 0..loops {
-        cnt += 1;
-        let _ = cnt;
-        if cnt % skip == 0 {
-            let _ = atomic.fetch_add(skip, Ordering::Relaxed);
-        } else {
-            let _ = atomic.load(Ordering::Relaxed);
-        }
+        black_box(atomic.fetch_add(1, Ordering::AcqRel));
     }
     now.elapsed()
 }
 
-fn sequencer(loops: usize, step: Sequence, lag_amp: Sequence) -> Duration {
-    let sequencer = match (step, lag_amp) {
+fn atomic_fence(loops: usize, atomic: Arc<AtomicUsize>) -> Duration {
+    let now = timer::start();
+    for _ in 0..loops {
+        black_box(atomic.fetch_add(1, Ordering::Relaxed));
+        fence(Ordering::Release);
+    }
+    now.elapsed()
+}
+
+fn atomic_skip(loops: usize, atomic: Arc<AtomicUsize>, skip: usize, units: usize) -> Duration {
+    let now = timer::start();
+    bench::atomic_skip(loops, &atomic, skip, units);
+    now.elapsed()
+}
+
+fn local_sequencer(step: Sequence, lag_amp: Sequence) -> &'static LocalKey<RefCell<Sequencer>> {
+    match (step, lag_amp) {
         (64, 8) => &SEQUENCER_64_8,
         (64, 16) => &SEQUENCER_64_16,
         (64, 32) => &SEQUENCER_64_32,
@@ -73,89 +142,1861 @@ fn sequencer(loops: usize, step: Sequence, lag_amp: Sequence) -> Duration {
         (128, 16) => &SEQUENCER_128_16,
         (128, 32) => &SEQUENCER_128_32,
         _ => unimplemented!(),
+    }
+}
+
+fn allocs(sequencer: &'static LocalKey<RefCell<Sequencer>>) -> Option<u64> {
+    sequencer.with(|s| own_allocs(&s.borrow()))
+}
+
+#[cfg(feature = "stats")]
+fn own_allocs(sequencer: &Sequencer) -> Option<u64> {
+    Some(sequencer.stats().allocs)
+}
+
+#[cfg(not(feature = "stats"))]
+fn own_allocs(_: &Sequencer) -> Option<u64> {
+    None
+}
+
+fn sequencer(loops: usize, step: Sequence, lag_amp: Sequence, units: usize) -> Sample {
+    let sequencer = local_sequencer(step, lag_amp);
+    let before = allocs(sequencer);
+    let now = timer::start();
+    bench::sequencer(loops, sequencer, units);
+    Sample::new(now.elapsed(), before, allocs(sequencer))
+}
+
+fn sequencer_dyn(loops: usize, step: Sequence, lag: Sequence) -> Sample {
+    SEQUENCER_DYN.with(|s| *s.borrow_mut() = Some(Sequencer::new(step, lag)));
+    let dyn_allocs = || SEQUENCER_DYN.with(|s| s.borrow().as_ref().and_then(own_allocs));
+    let before = dyn_allocs();
+    let now = timer::start();
+    for _ in 0..loops {
+        black_box(SEQUENCER_DYN.with(|s| s.borrow_mut().as_mut().unwrap().inc()));
+    }
+    Sample::new(now.elapsed(), before, dyn_allocs())
+}
+
+fn sequencer_read_global(loops: usize, step: Sequence, lag_amp: Sequence) -> Sample {
+    let sequencer = local_sequencer(step, lag_amp);
+    let before = allocs(sequencer);
+    let now = timer::start();
+    for _ in 0..loops {
+        black_box(sequencer.with(|s| {
+            let mut s = s.borrow_mut();
+            (s.inc(), s.global())
+        }));
+    }
+    Sample::new(now.elapsed(), before, allocs(sequencer))
+}
+
+// Sequence `n` of shard `shard` out of `shards` is `n * shards + shard`, so that every shard
+// counter can be contended on its own without two shards ever handing out the same value.
+fn sequencer_sharded(
+    loops: usize,
+    step: Sequence,
+    lag: Sequence,
+    global: Arc<AtomicSequence>,
+    shard: Sequence,
+    shards: Sequence,
+) -> Duration {
+    let mut sequencer = Sequencer::new(step, lag);
+    sequencer.rebase(global);
+    let now = timer::start();
+    for _ in 0..loops {
+        black_box(sequencer.inc() * shards + shard);
+    }
+    now.elapsed()
+}
+
+fn sequencer_prefetch(loops: usize, step: Sequence, lag: Sequence, depth: usize) -> Duration {
+    let mut sequencer = Sequencer::new_buffered(step, lag, depth);
+    let now = timer::start();
+    for _ in 0..loops {
+        sequencer.prefetch_next_window();
+        black_box(sequencer.inc());
+    }
+    now.elapsed()
+}
+
+// A fresh sequencer per inc, as in a short-lived task: every inc pays for a window.
+fn sequencer_cold(loops: usize, step: Sequence, lag: Sequence) -> Duration {
+    let now = timer::start();
+    for _ in 0..loops {
+        let mut sequencer = black_box(Sequencer::new(step, lag));
+        black_box(sequencer.inc());
+    }
+    now.elapsed()
+}
+
+// The same parameters, with one sequencer reused for every inc.
+fn sequencer_hot(loops: usize, step: Sequence, lag: Sequence) -> Duration {
+    let mut sequencer = Sequencer::new(step, lag);
+    let now = timer::start();
+    for _ in 0..loops {
+        black_box(sequencer.inc());
+    }
+    now.elapsed()
+}
+
+// A fresh window on every inc has to cost more than serving from one: a cold path measuring
+// no slower than the hot one at the same thread count means the benchmark is off, e.g.
+// because the fresh sequencers were optimized away.
+fn cold_not_slower(results: &[BenchResult]) -> Option<String> {
+    let ns = |name: &str| {
+        results
+            .iter()
+            .find(|result| result.name == name)
+            .map(|result| result.ns_per_iter)
     };
-    let now = Instant::now();
+    let (cold, hot) = (ns("sequencer cold")?, ns("sequencer hot")?);
+    (cold <= hot).then(|| {
+        format!(
+            "sequencer cold ({:.2}ns per iter) is not slower than sequencer hot ({:.2}ns) at {} threads",
+            cold, hot, results[0].threads
+        )
+    })
+}
+
+// Times every inc on its own, so the result has a distribution, not just a mean. Reading
+// the clock is part of both the mean and the quantiles, feeding the estimators only of the
+// mean.
+fn sequencer_latency(loops: usize, step: Sequence, lag: Sequence) -> Sample {
+    let mut sequencer = Sequencer::new(step, lag);
+    let (mut p50, mut p99) = (QuantileEstimator::new(0.5), QuantileEstimator::new(0.99));
+    let now = timer::start();
     for _ in 0..loops {
-        let _ = sequencer.with(|s| s.borrow_mut().inc());
+        let inc = timer::start();
+        black_box(sequencer.inc());
+        let ns = inc.elapsed().as_nanos() as f64;
+        p50.push(ns);
+        p99.push(ns);
     }
+    let mut sample = Sample::from(now.elapsed());
+    sample.quantiles = p50.estimate().zip(p99.estimate());
+    sample
+}
+
+fn shared_mutex(loops: usize, sequencer: Arc<SharedSequencer>) -> Duration {
+    let now = timer::start();
+    bench::shared_mutex(loops, &sequencer);
     now.elapsed()
 }
 
-fn benchmark<F>(name: &str, threads: usize, loops: usize, f: F)
+fn shared_lock_free(loops: usize, sequencer: Arc<LockFreeSequencer>) -> Duration {
+    let now = timer::start();
+    bench::shared_lock_free(loops, &sequencer);
+    now.elapsed()
+}
+
+/// What one benchmark thread measured.
+#[derive(Default)]
+struct Sample {
+    elapsed: Duration,
+    /// Global `fetch_add`s issued by a sequencer, with the `stats` feature.
+    allocs: Option<u64>,
+    /// Whether the thread ended on another CPU than it started on, with `migration`.
+    migrated: Option<bool>,
+    /// p50 and p99 of single iterations, for benchmarks that time each of them.
+    quantiles: Option<(f64, f64)>,
+}
+
+impl Sample {
+    fn new(elapsed: Duration, before: Option<u64>, after: Option<u64>) -> Self {
+        Self {
+            elapsed,
+            allocs: after.zip(before).map(|(after, before)| after - before),
+            migrated: None,
+            quantiles: None,
+        }
+    }
+}
+
+impl From<Duration> for Sample {
+    fn from(elapsed: Duration) -> Self {
+        Self {
+            elapsed,
+            allocs: None,
+            migrated: None,
+            quantiles: None,
+        }
+    }
+}
+
+// Runs `f(i)` on thread `i` of `threads`, all timing loops overlapping.
+fn benchmark<F, R>(
+    executor: &dyn Executor,
+    name: &str,
+    threads: usize,
+    loops: usize,
+    f: F,
+) -> BenchResult
 where
-    F: Fn() -> Duration + Clone + Send + 'static,
+    F: Fn(usize) -> R + Clone + Send + 'static,
+    R: Into<Sample> + Send + 'static,
 {
-    let handles = (0..threads)
-        .map(|_| std::thread::spawn(black_box(f.clone())))
-        .collect_vec();
+    // Per iter costs only cover the timing loop inside each thread. Spawning, the barrier
+    // and joining are measured separately as the wall time the longest loop doesn't explain.
+    let start = Instant::now();
+    let thread_samples = run_together(executor, threads, move |i| {
+        let f = black_box(f.clone());
+        pin_current(i);
+        move || {
+            let cpu = current_cpu();
+            let mut sample: Sample = f(i).into();
+            sample.migrated = cpu.zip(current_cpu()).map(|(start, end)| start != end);
+            sample
+        }
+    });
     let mut dur = Duration::from_nanos(0);
-    for handle in handles {
-        dur += handle.join().unwrap();
+    let mut longest = Duration::ZERO;
+    let mut allocs = None;
+    let mut migrations = None;
+    let mut quantiles: Option<(f64, f64)> = None;
+    let mut samples = vec![];
+    for sample in thread_samples {
+        samples.push(sample.elapsed.as_nanos() as f64 / loops as f64);
+        dur += sample.elapsed;
+        longest = longest.max(sample.elapsed);
+        allocs = sample.allocs.map(|a| a + allocs.unwrap_or(0));
+        migrations = sample
+            .migrated
+            .map(|m| m as usize + migrations.unwrap_or(0));
+        quantiles = sample.quantiles.map(|(p50, p99)| match quantiles {
+            Some((a, b)) => (p50.max(a), p99.max(b)),
+            None => (p50, p99),
+        });
+    }
+    BenchResult {
+        name: name.to_string(),
+        threads,
+        loops,
+        ns_per_iter: ns_per_iter(dur, threads, loops),
+        allocs,
+        migrations,
+        quantiles,
+        spawn_join: start.elapsed().saturating_sub(longest),
+        samples,
+    }
+}
+
+// Below this per thread timing loop duration the cost is mostly timer resolution.
+const MIN_MEASURABLE: Duration = Duration::from_millis(1);
+const MAX_LOOP_SCALE: usize = 1000;
+
+// Runs `measure` with ten times the loops while its timing loops are too short to be
+// trusted, up to `MAX_LOOP_SCALE` times the requested loops. The result reports the loops
+// it was measured with.
+fn auto_scaled(loops: usize, mut measure: impl FnMut(usize) -> BenchResult) -> BenchResult {
+    let mut scaled = loops;
+    loop {
+        let result = measure(scaled);
+        let per_thread = Duration::from_secs_f64(result.ns_per_iter * scaled as f64 / 1e9);
+        if per_thread >= MIN_MEASURABLE || scaled >= loops.saturating_mul(MAX_LOOP_SCALE) {
+            return result;
+        }
+        scaled = scaled.saturating_mul(10);
+    }
+}
+
+// Per benchmark time of the timing loops with `--ci`, whatever the speed of the machine.
+const CI_BUDGET: Duration = Duration::from_millis(200);
+const CI_CALIBRATION_LOOPS: usize = 1000;
+
+// Measures with the loops that make the timing loops last about `budget`, as estimated from
+// a calibration run that `auto_scaled` made long enough to be timed.
+fn budgeted(budget: Duration, mut measure: impl FnMut(usize) -> BenchResult) -> BenchResult {
+    let calibration = auto_scaled(CI_CALIBRATION_LOOPS, &mut measure);
+    measure(budget_loops(budget, &calibration))
+}
+
+fn budget_loops(budget: Duration, calibration: &BenchResult) -> usize {
+    if calibration.ns_per_iter <= 0.0 {
+        return calibration.loops.saturating_mul(MAX_LOOP_SCALE);
+    }
+    ((budget.as_nanos() as f64 / calibration.ns_per_iter) as usize).max(1)
+}
+
+// `budgeted` if there is a budget, `auto_scaled` from `loops` otherwise.
+fn sized(
+    loops: usize,
+    budget: Option<Duration>,
+    measure: impl FnMut(usize) -> BenchResult,
+) -> BenchResult {
+    match budget {
+        Some(budget) => budgeted(budget, measure),
+        None => auto_scaled(loops, measure),
+    }
+}
+
+const STRESS_ROUNDS: usize = 2;
+
+// Every thread draws from `draw`; the merged output must be free of duplicates and fit
+// below the final global head. On a pool the later rounds of the thread-local `SEQUENCER`
+// keep drawing from windows left over by the earlier ones.
+fn stress<D>(executor: &dyn Executor, name: &str, threads: usize, loops: usize, draw: D)
+where
+    D: Fn() -> Sequence + Clone + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..STRESS_ROUNDS {
+        let jobs = (0..threads)
+            .map(|_| {
+                let tx = tx.clone();
+                let draw = draw.clone();
+                Box::new(move || {
+                    let seqs = (0..loops).map(|_| draw()).collect_vec();
+                    tx.send(seqs).unwrap();
+                }) as Job
+            })
+            .collect_vec();
+        executor.execute(jobs);
     }
+    drop(tx);
+    let mut all = rx.iter().flatten().collect_vec();
+    let total = all.len() as Sequence;
+    all.sort_unstable();
+    all.dedup();
+    assert_eq!(all.len() as Sequence, total, "duplicate sequences");
+
+    let head = SEQUENCE_GLOBAL.load(Ordering::Relaxed);
+    let span = all[all.len() - 1] - all[0] + 1;
+    assert!(span >= total && all[all.len() - 1] < head);
     println!(
-        "{:20} {} threads {} loops: {:?} per iter",
+        "stress {}: {} rounds x {} threads x {} loops: {} unique sequences, span {} ({} unused)",
         name,
+        STRESS_ROUNDS,
         threads,
         loops,
-        Duration::from_nanos((dur.as_nanos() / threads as u128 / loops as u128) as u64)
+        total,
+        span,
+        span - total
     );
 }
 
-fn main() {
-    for (threads, loops) in [
-        (1, 10_000_000),
-        (4, 10_000_000),
-        (8, 10_000_000),
-        (16, 10_000_000),
-        (32, 10_000_000),
-    ] {
-        println!();
+// Every thread bumps a shared atomic counter and draws a sequence from its thread-local
+// sequencer `loops` times. Both must account for the same work: as many unique sequences
+// as counter increments, or some were lost or handed out twice.
+fn verify(executor: &dyn Executor, threads: usize, loops: usize) -> Result<usize, String> {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel();
+    let jobs = (0..threads)
+        .map(|_| {
+            let counter = counter.clone();
+            let tx = tx.clone();
+            Box::new(move || {
+                let seqs = (0..loops)
+                    .map(|_| {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                        SEQUENCER.with(|s| s.borrow_mut().inc())
+                    })
+                    .collect_vec();
+                tx.send(seqs).unwrap();
+            }) as Job
+        })
+        .collect_vec();
+    executor.execute(jobs);
+    drop(tx);
+    let mut all = rx.iter().flatten().collect_vec();
+    let drawn = all.len();
+    all.sort_unstable();
+    all.dedup();
+    let (expected, unique) = (counter.load(Ordering::Relaxed), all.len());
+    if unique != expected {
+        return Err(format!(
+            "verify: {} unique sequences for {} counter increments ({} drawn)",
+            unique, expected, drawn
+        ));
+    }
+    Ok(unique)
+}
 
-        benchmark("primitive", threads, loops, move || primitive(loops));
+// Every thread creates `loops` short-lived sequencers on a fresh global, takes 1 to 16
+// sequences from each and then drops it, or forgets it so the rest of its window is never
+// handed back. Returns the global head afterwards and how many sequences each thread took.
+// Threads draw from `seed`, so both passes do the same work.
+fn churn(
+    executor: &dyn Executor,
+    threads: usize,
+    loops: usize,
+    seed: u64,
+    reclaim: bool,
+) -> (Sequence, Vec<Sequence>) {
+    let global = Arc::new(AtomicSequence::new(0));
+    let setup = {
+        let global = global.clone();
+        move |i| {
+            let global = global.clone();
+            move || {
+                let mut rng = XorShift::for_thread(seed, i);
+                let mut taken = 0;
+                for _ in 0..loops {
+                    let mut sequencer = Sequencer::with_backend(global.clone(), 128, 128 * 16);
+                    for _ in 0..=rng.next_u64() % 16 {
+                        black_box(sequencer.inc());
+                        taken += 1;
+                    }
+                    if reclaim {
+                        drop(sequencer);
+                    } else {
+                        std::mem::forget(sequencer);
+                    }
+                }
+                (i, taken)
+            }
+        }
+    };
+    let mut taken = run_together(executor, threads, setup);
+    taken.sort_unstable();
+    let taken = taken.into_iter().map(|(_, taken)| taken).collect();
+    (global.load(Ordering::Relaxed), taken)
+}
 
-        let a = Arc::new(AtomicUsize::new(0));
-        benchmark("atomic", threads, loops, move || atomic(loops, a.clone()));
+const MATRIX: [(usize, usize); 5] = [
+    (1, 10_000_000),
+    (4, 10_000_000),
+    (8, 10_000_000),
+    (16, 10_000_000),
+    (32, 10_000_000),
+];
 
-        let a = Arc::new(AtomicUsize::new(0));
-        benchmark("atomic skip 8", threads, loops, move || {
-            atomic_skip(loops, a.clone(), 8)
-        });
+// Thread counts beyond the core count only measure oversubscription, so cap them unless
+// asked otherwise. If the core count is unknown, run the full matrix.
+fn matrix(cores: Option<usize>, oversubscribe: bool) -> Vec<(usize, usize)> {
+    match cores {
+        Some(cores) if !oversubscribe => MATRIX
+            .iter()
+            .map(|&(threads, loops)| (threads.min(cores), loops))
+            .dedup_by(|a, b| a.0 == b.0)
+            .collect(),
+        _ => MATRIX.to_vec(),
+    }
+}
 
-        let a = Arc::new(AtomicUsize::new(0));
-        benchmark("atomic skip 16", threads, loops, move || {
-            atomic_skip(loops, a.clone(), 16)
-        });
+type BoxedBench = Box<dyn Fn() -> Sample + Send + Sync>;
+type MakeBench = Box<dyn Fn(usize, usize) -> BoxedBench>;
 
-        let a = Arc::new(AtomicUsize::new(0));
-        benchmark("atomic skip 32", threads, loops, move || {
-            atomic_skip(loops, a.clone(), 32)
-        });
+// Name, one-line description and constructor of every benchmark, in run order. A
+// benchmark's counters are only created when it is built, for `loops` iterations with
+// `work` units of synthetic work each.
+#[derive(Default)]
+struct Registry(Vec<(String, String, MakeBench)>);
 
-        let a = Arc::new(AtomicUsize::new(0));
-        benchmark("atomic skip 64", threads, loops, move || {
-            atomic_skip(loops, a.clone(), 64)
+impl Registry {
+    fn register<M, F, R>(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        make: M,
+    ) where
+        M: Fn(usize, usize) -> F + 'static,
+        F: Fn() -> R + Send + Sync + 'static,
+        R: Into<Sample>,
+    {
+        self.0.push((
+            name.into(),
+            description.into(),
+            Box::new(move |loops, work| {
+                let f = make(loops, work);
+                Box::new(move || f().into())
+            }),
+        ));
+    }
+
+    fn build(&self, name: &str, loops: usize, work: usize) -> Option<BoxedBench> {
+        self.0
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, _, make)| make(loops, work))
+    }
+}
+
+fn benchmark_boxed(
+    executor: &dyn Executor,
+    name: &str,
+    threads: usize,
+    loops: usize,
+    f: BoxedBench,
+) -> BenchResult {
+    let f: Arc<dyn Fn() -> Sample + Send + Sync> = Arc::from(f);
+    benchmark(executor, name, threads, loops, move |_| f())
+}
+
+// The counter of `atomic static`, shared by every run of it.
+static ATOMIC_STATIC: AtomicUsize = AtomicUsize::new(0);
+
+// `work` units of synthetic work follow every counter operation of `atomic`, `atomic skip`
+// and `sequencer`.
+fn registry() -> Registry {
+    let mut registry = Registry::default();
+
+    registry.register(
+        "primitive",
+        "a local counter, the cost of the timing loop itself",
+        |loops, _| move || primitive(loops),
+    );
+
+    registry.register(
+        "atomic",
+        "relaxed fetch_add on one shared counter",
+        |loops, work| {
+            let a = Arc::new(AtomicUsize::new(0));
+            move || atomic(loops, a.clone(), work)
+        },
+    );
+    registry.register(
+        "atomic static",
+        "atomic on a static counter, no Arc refcount next to it",
+        |loops, work| move || atomic(loops, &ATOMIC_STATIC, work),
+    );
+    registry.register(
+        "atomic load",
+        "relaxed loads of one shared counter, never written",
+        |loops, _| {
+            let a = Arc::new(AtomicUsize::new(0));
+            move || atomic_load(loops, a.clone())
+        },
+    );
+    registry.register(
+        "atomic acqrel",
+        "acquire-release fetch_add on one shared counter",
+        |loops, _| {
+            let a = Arc::new(AtomicUsize::new(0));
+            move || atomic_acq_rel(loops, a.clone())
+        },
+    );
+    registry.register(
+        "atomic+fence",
+        "relaxed fetch_add followed by a release fence",
+        |loops, _| {
+            let a = Arc::new(AtomicUsize::new(0));
+            move || atomic_fence(loops, a.clone())
+        },
+    );
+
+    for skip in [8, 16, 32, 64] {
+        registry.register(
+            format!("atomic skip {}", skip),
+            format!(
+                "fetch_add of {} every {} iters, loads in between",
+                skip, skip
+            ),
+            move |loops, work| {
+                let a = Arc::new(AtomicUsize::new(0));
+                move || atomic_skip(loops, a.clone(), skip, work)
+            },
+        );
+    }
+
+    for (step, lag_amp) in [(64, 8), (64, 16), (64, 32), (128, 8), (128, 16), (128, 32)] {
+        registry.register(
+            format!("sequencer({},{})", step, lag_amp),
+            format!(
+                "thread-local sequencer, step {}, lag {} steps",
+                step, lag_amp
+            ),
+            move |loops, work| move || sequencer(loops, step, lag_amp, work),
+        );
+    }
+    // Fixed step, so that only the lag varies.
+    for lag in [128, 512, 2048, 8192, Sequencer::NO_LAG] {
+        let (name, description) = match lag {
+            Sequencer::NO_LAG => (
+                "seq(128) lag none".to_string(),
+                "thread-local sequencer, step 128, no lag check".to_string(),
+            ),
+            lag => (
+                format!("seq(128) lag {}", lag),
+                format!("thread-local sequencer, step 128, lag {}", lag),
+            ),
+        };
+        registry.register(name, description, move |loops, _| {
+            move || sequencer_dyn(loops, 128, lag)
         });
+    }
+    registry.register(
+        "seq+global(128,16)",
+        "sequencer(128,16) also reading the global head every inc",
+        |loops, _| move || sequencer_read_global(loops, 128, 16),
+    );
+
+    registry.register(
+        "sequencer cold",
+        "a fresh sequencer for every inc",
+        |loops, _| move || sequencer_cold(loops, 128, 128 * 16),
+    );
+    registry.register(
+        "sequencer hot",
+        "one sequencer owned by the loop, reused for every inc",
+        |loops, _| move || sequencer_hot(loops, 128, 128 * 16),
+    );
+    registry.register(
+        "sequencer prefetch",
+        "sequencer hot extending its window ahead of time",
+        |loops, _| move || sequencer_prefetch(loops, 128, 128 * 16, 0),
+    );
+    registry.register(
+        "sequencer prefetch 2",
+        "sequencer prefetch keeping two whole windows staged ahead",
+        |loops, _| move || sequencer_prefetch(loops, 128, 128 * 16, 2),
+    );
+    registry.register(
+        "sequencer latency",
+        "sequencer hot timing every inc, with p50/p99",
+        |loops, _| move || sequencer_latency(loops, 128, 128 * 16),
+    );
+
+    // Hypothesis: the window check at the top of every inc is close to free while it is
+    // predictable. Both run without the lag check, so that the check is the only branch:
+    // with a huge step it is practically never taken, with a step of 2 it is taken every
+    // other inc. If the gap between them is mostly the `fetch_add` the taken branch leads
+    // to, rather than mispredictions, a branchless fast path would buy nothing.
+    registry.register(
+        "inc branch rare",
+        "no-lag inc whose window check practically never fails",
+        |loops, _| move || sequencer_hot(loops, 1 << 30, Sequencer::NO_LAG),
+    );
+    registry.register(
+        "inc branch often",
+        "no-lag inc whose window check fails every other inc",
+        |loops, _| move || sequencer_hot(loops, 2, Sequencer::NO_LAG),
+    );
+
+    registry.register(
+        "shared mutex(128)",
+        "one sequencer behind a mutex, shared by all threads",
+        |loops, _| {
+            let s = Arc::new(SharedSequencer::new(128, 128 * 16));
+            move || shared_mutex(loops, s.clone())
+        },
+    );
+    registry.register(
+        "shared cas(128)",
+        "one lock-free sequencer shared by all threads",
+        |loops, _| {
+            let s = Arc::new(LockFreeSequencer::new(128));
+            move || shared_lock_free(loops, s.clone())
+        },
+    );
+
+    registry.register(
+        "coarse",
+        "coarsetime::Instant::now, for scale",
+        |loops, _| move || coarse(loops),
+    );
+
+    registry
+}
+
+const PRIMITIVE_MONO: &str = "primitive mono";
+
+// Every benchmark `--only` accepts, with its description.
+fn benchmark_list() -> Vec<(String, String)> {
+    let mut list = registry()
+        .0
+        .into_iter()
+        .map(|(name, description, _)| (name, description))
+        .collect_vec();
+    list.push((
+        PRIMITIVE_MONO.to_string(),
+        "primitive without the boxed call, to show the harness adds nothing".to_string(),
+    ));
+    list
+}
+
+fn benchmark_names() -> Vec<String> {
+    benchmark_list().into_iter().map(|(name, _)| name).collect()
+}
+
+fn list() -> String {
+    benchmark_list()
+        .into_iter()
+        .map(|(name, description)| format!("{:20} {}\n", name, description))
+        .collect()
+}
+
+// Hands every result to `done` as soon as its benchmark finishes. With a `budget`, the
+// loops of every benchmark are picked to fill it and `loops` is ignored.
+fn run(
+    executor: &dyn Executor,
+    threads: usize,
+    loops: usize,
+    budget: Option<Duration>,
+    work: usize,
+    only: Option<&[String]>,
+    mut done: impl FnMut(BenchResult),
+) {
+    let selected = |name: &str| only.is_none_or(|only| only.iter().any(|n| n == name));
+
+    let registry = registry();
+    for (name, _, _) in &registry.0 {
+        if selected(name) {
+            done(sized(loops, budget, |loops| {
+                let f = registry.build(name, loops, work).unwrap();
+                benchmark_boxed(executor, name, threads, loops, f)
+            }));
+        }
+    }
+
+    // Registered benchmarks go through a `dyn Fn` call once per thread, not per iteration;
+    // the monomorphized twin of the cheapest benchmark shows the harness cost is the same.
+    if selected(PRIMITIVE_MONO) {
+        done(sized(loops, budget, |loops| {
+            benchmark(executor, PRIMITIVE_MONO, threads, loops, move |_| {
+                primitive(loops)
+            })
+        }));
+    }
+}
+
+const SCAN_TOLERANCE: f64 = 0.05;
+const SCAN_MAX_THREADS: usize = 256;
+
+// Doubles the thread count while aggregate throughput keeps improving by more than
+// `tolerance`, and returns the thread count and throughput at the peak.
+fn scan(mut throughput: impl FnMut(usize) -> f64, tolerance: f64, max: usize) -> (usize, f64) {
+    let mut peak = (1, throughput(1));
+    while peak.0 * 2 <= max {
+        let threads = peak.0 * 2;
+        let t = throughput(threads);
+        if t <= peak.1 * (1.0 + tolerance) {
+            break;
+        }
+        peak = (threads, t);
+    }
+    peak
+}
+
+fn scan_benchmark(
+    executor: &dyn Executor,
+    name: &str,
+    loops: usize,
+) -> Result<(usize, f64), String> {
+    let registry = registry();
+    if !registry.0.iter().any(|(n, _, _)| n == name) {
+        return Err(format!("unknown benchmark: {}", name));
+    }
+    let throughput = |threads| {
+        let f = registry.build(name, loops, 0).unwrap();
+        let result = benchmark_boxed(executor, name, threads, loops, f);
+        let ops = threads as f64 * 1e9 / result.ns_per_iter;
+        println!("{:20} {} threads: {:.2} Mops/s", name, threads, ops / 1e6);
+        ops
+    };
+    Ok(scan(throughput, SCAN_TOLERANCE, SCAN_MAX_THREADS))
+}
+
+const SHARD_COUNTS: [usize; 5] = [1, 2, 4, 8, 16];
+const SHARD_THREADS: usize = 32;
+// Small windows, so that the global counters are hit often enough for sharding to matter.
+const SHARD_STEP: Sequence = 16;
+
+// Runs `SHARD_THREADS` threads spread round robin over each count of global counters, and
+// returns the per iter cost of every count.
+fn shard_sweep(executor: &dyn Executor, loops: usize) -> Vec<(usize, BenchResult)> {
+    SHARD_COUNTS
+        .iter()
+        .map(|&shards| {
+            let globals = Arc::new(
+                (0..shards)
+                    .map(|_| Arc::new(AtomicSequence::new(0)))
+                    .collect_vec(),
+            );
+            let name = format!("{} shards", shards);
+            let result = benchmark(executor, &name, SHARD_THREADS, loops, move |i| {
+                let shard = i % shards;
+                sequencer_sharded(
+                    loops,
+                    SHARD_STEP,
+                    SHARD_STEP * 16,
+                    globals[shard].clone(),
+                    shard as Sequence,
+                    shards as Sequence,
+                )
+            });
+            (shards, result)
+        })
+        .collect()
+}
+
+const HEAD_TO_HEAD_STEP: Sequence = 128;
+const HEAD_TO_HEAD_SHARDS: usize = 4;
+
+// Hands `fill(i, seqs)` on thread `i` a buffer for the `loops` sequences it draws, then
+// checks those of all threads together for duplicates. Returns the result and every drawn
+// sequence, in order.
+fn unique_draws<F, R>(
+    executor: &dyn Executor,
+    name: &str,
+    threads: usize,
+    loops: usize,
+    fill: F,
+) -> (BenchResult, Vec<Sequence>)
+where
+    F: Fn(usize, &mut [Sequence]) -> R + Clone + Send + 'static,
+    R: Into<Sample> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let result = benchmark(executor, name, threads, loops, move |i| {
+        // Written once up front, so that page faults stay out of the timing loop.
+        let mut seqs = vec![Sequence::MAX; loops];
+        let sample = fill(i, &mut seqs);
+        tx.send(seqs).unwrap();
+        sample
+    });
+    let mut all = rx.iter().flatten().collect_vec();
+    all.sort_unstable();
+    all.dedup();
+    assert_eq!(
+        all.len(),
+        threads * loops,
+        "{}: lost or duplicate sequences",
+        name
+    );
+    (result, all)
+}
+
+// Times one coordination scheme of `head_to_head`: thread `i` draws `loops` sequences from
+// `draw_for(i)`, keeping them so that the whole output can be checked for duplicates. A
+// store per draw is part of the cost, the same for every scheme.
+fn head_to_head_run<M, D>(
+    executor: &dyn Executor,
+    name: &str,
+    threads: usize,
+    loops: usize,
+    draw_for: M,
+) -> BenchResult
+where
+    M: Fn(usize) -> D + Clone + Send + 'static,
+    D: FnMut() -> Sequence,
+{
+    let (result, _) = unique_draws(executor, name, threads, loops, move |i, seqs| {
+        let mut draw = draw_for(i);
+        let now = timer::start();
+        for seq in seqs.iter_mut() {
+            *seq = draw();
+        }
+        now.elapsed()
+    });
+    result
+}
+
+// The thread-local sequencer, one lock-free sequencer shared by all threads, and
+// thread-local sequencers spread over `HEAD_TO_HEAD_SHARDS` counters, all with the same
+// step, at each of the thread counts.
+fn head_to_head(executor: &dyn Executor, threads: &[usize], loops: usize) -> Vec<[BenchResult; 3]> {
+    threads
+        .iter()
+        .map(|&threads| {
+            let global = Arc::new(AtomicSequence::new(0));
+            let local = head_to_head_run(executor, "thread-local", threads, loops, move |_| {
+                let mut sequencer = Sequencer::with_backend(
+                    global.clone(),
+                    HEAD_TO_HEAD_STEP,
+                    HEAD_TO_HEAD_STEP * 16,
+                );
+                move || sequencer.inc()
+            });
+            let shared = Arc::new(LockFreeSequencer::new(HEAD_TO_HEAD_STEP));
+            let lock_free = head_to_head_run(executor, "lock-free", threads, loops, move |_| {
+                let shared = shared.clone();
+                move || shared.inc()
+            });
+            let globals = Arc::new(
+                (0..HEAD_TO_HEAD_SHARDS)
+                    .map(|_| Arc::new(AtomicSequence::new(0)))
+                    .collect_vec(),
+            );
+            let sharded = head_to_head_run(executor, "sharded", threads, loops, move |i| {
+                let shard = i % HEAD_TO_HEAD_SHARDS;
+                let mut sequencer = Sequencer::with_backend(
+                    globals[shard].clone(),
+                    HEAD_TO_HEAD_STEP,
+                    HEAD_TO_HEAD_STEP * 16,
+                );
+                let shards = HEAD_TO_HEAD_SHARDS as Sequence;
+                move || sequencer.inc() * shards + shard as Sequence
+            });
+            [local, lock_free, sharded]
+        })
+        .collect()
+}
+
+// The dynamic sequencer with thread `i` on `params[i % params.len()]`, so that hot threads
+// with small windows contend with cold ones with large windows.
+fn mixed(executor: &dyn Executor, threads: usize, loops: usize, params: &[StepLag]) -> BenchResult {
+    let params = params.to_vec();
+    benchmark(executor, "sequencer mixed", threads, loops, move |i| {
+        let StepLag { step, lag } = params[i % params.len()];
+        sequencer_dyn(loops, step, lag)
+    })
+}
+
+const GLOBAL_CHURN_STEP: Sequence = 128;
+/// Bounds the churner's record of its windows; far more than a run takes to finish.
+const GLOBAL_CHURN_WINDOWS: usize = 1 << 22;
+
+// Each thread draws `loops` sequences from its own sequencer with the tightest lag while, with
+// `churn`, one more thread keeps moving `SEQUENCE_GLOBAL` on by a window at a time, the way
+// a crowd of other allocators would: nearly every draw then takes the lag trip path, which
+// the symmetric benchmarks hardly ever do. Checks that the drawn sequences are unique and
+// clear of every window the churner took, and returns how many that was.
+fn global_churn(
+    executor: &dyn Executor,
+    threads: usize,
+    loops: usize,
+    churn: bool,
+) -> (BenchResult, usize) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let churner = churn.then(|| {
+        let stop = stop.clone();
+        std::thread::spawn(move || {
+            let mut taken = Vec::with_capacity(GLOBAL_CHURN_WINDOWS);
+            while !stop.load(Ordering::Relaxed) && taken.len() < GLOBAL_CHURN_WINDOWS {
+                match SEQUENCE_GLOBAL.allocate(GLOBAL_CHURN_STEP) {
+                    Some(start) => taken.push(start),
+                    None => break,
+                }
+            }
+            taken
+        })
+    });
+
+    let name = if churn {
+        "sequencer churned"
+    } else {
+        "sequencer quiet"
+    };
+    let (result, all) = unique_draws(executor, name, threads, loops, |_, seqs| {
+        let mut sequencer = Sequencer::new(GLOBAL_CHURN_STEP, GLOBAL_CHURN_STEP);
+        let now = timer::start();
+        for seq in seqs.iter_mut() {
+            *seq = sequencer.inc();
+        }
+        Sample::new(now.elapsed(), Some(0), own_allocs(&sequencer))
+    });
+    stop.store(true, Ordering::Relaxed);
+    // In allocation order, so ascending.
+    let taken = churner.map(|c| c.join().unwrap()).unwrap_or_default();
+
+    for &seq in &all {
+        let i = taken.partition_point(|&start| start <= seq);
+        assert!(
+            i == 0 || seq >= taken[i - 1] + GLOBAL_CHURN_STEP,
+            "{}: sequence {} is in the churner's window at {}",
+            name,
+            seq,
+            taken[i - 1]
+        );
+    }
+    (result, taken.len())
+}
+
+const HEATMAP_STEPS: [Sequence; 7] = [16, 32, 64, 128, 256, 512, 1024];
+const HEATMAP_LAG_AMP: Sequence = 16;
+
+// Also hands every cell to `done`, with its step, as soon as it is measured.
+fn heatmap(
+    executor: &dyn Executor,
+    steps: &[Sequence],
+    matrix: &[(usize, usize)],
+    mut done: impl FnMut(Sequence, &BenchResult),
+) -> Heatmap {
+    let cells = steps
+        .iter()
+        .map(|&step| {
+            matrix
+                .iter()
+                .map(|&(threads, loops)| {
+                    let lag = step * HEATMAP_LAG_AMP;
+                    let result = benchmark(executor, "heatmap", threads, loops, move |_| {
+                        sequencer_dyn(loops, step, lag)
+                    });
+                    done(step, &result);
+                    result.ns_per_iter
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+    Heatmap {
+        steps: steps.to_vec(),
+        threads: matrix.iter().map(|&(threads, _)| threads).collect(),
+        lag_amp: HEATMAP_LAG_AMP,
+        cells,
+    }
+}
+
+const CALIBRATION_RUNS: usize = 3;
+
+// Per iter cost of the bare timing loop, which every benchmark pays on top of what it
+// measures. The cheapest of a few single threaded runs, as noise only ever adds to it.
+fn loop_overhead(loops: usize) -> f64 {
+    (0..CALIBRATION_RUNS)
+        .map(|_| ns_per_iter(primitive(loops), 1, loops))
+        .fold(f64::INFINITY, f64::min)
+}
+
+// The `(threads, loops)` pairs to run, after the command line overrides.
+fn resolve_matrix(args: &Args) -> Vec<(usize, usize)> {
+    let cores = std::thread::available_parallelism().ok().map(|n| n.get());
+    let mut matrix = match &args.threads {
+        Some(threads) => threads
+            .iter()
+            .map(|&threads| (threads, MATRIX[0].1))
+            .collect(),
+        None => matrix(cores, args.oversubscribe),
+    };
+    if let Some(loops) = args.loops {
+        matrix.iter_mut().for_each(|(_, l)| *l = loops);
+    }
+    if args.only_last {
+        matrix = matrix
+            .into_iter()
+            .max_by_key(|&(threads, _)| threads)
+            .into_iter()
+            .collect();
+    }
+    matrix
+}
+
+// What `main` runs for `args`, in a few words.
+fn mode(args: &Args) -> String {
+    if args.stress {
+        format!("stress, 8 threads x {} loops", args.stress_loops)
+    } else if args.verify {
+        format!("verify, 8 threads x {} loops", args.stress_loops)
+    } else if args.churn {
+        format!("churn, 8 threads x {} loops", args.stress_loops)
+    } else if let Some(name) = &args.scan {
+        format!("scan {}", name)
+    } else if args.head_to_head {
+        format!("head-to-head, {} loops", args.stress_loops)
+    } else if args.shards {
+        format!("shards {:?}, {} threads", SHARD_COUNTS, SHARD_THREADS)
+    } else if let Some(params) = &args.mixed {
+        format!("mixed {}", params.iter().join(","))
+    } else if args.global_churn {
+        format!("global churn, {} loops", args.stress_loops)
+    } else if args.heatmap {
+        "heatmap".to_string()
+    } else {
+        "benchmarks".to_string()
+    }
+}
+
+// Validates what `main` would do and describes it, without running anything.
+fn dry_run(args: &Args) -> Result<String, String> {
+    let mut plan = String::new();
+    let names = benchmark_names();
+    if let Some(name) = &args.scan {
+        if !names.contains(name) {
+            return Err(format!("unknown benchmark: {}", name));
+        }
+    }
+    if let Some(unknown) = args
+        .benchmarks
+        .iter()
+        .flatten()
+        .find(|name| !names.contains(name))
+    {
+        return Err(format!("unknown benchmark: {}", unknown));
+    }
+    if let Some(path) = &args.baseline {
+        baseline::load(path)?;
+    }
+    if let Some(path) = &args.save_baseline {
+        writable(path)?;
+    }
 
-        benchmark("sequencer(64,8)", threads, loops, move || {
-            sequencer(loops, 64, 8)
+    let mode = mode(args);
+    writeln!(plan, "mode: {}", mode).unwrap();
+    let selected = match &args.benchmarks {
+        Some(only) => only.clone(),
+        None => names,
+    };
+    writeln!(plan, "benchmarks: {}", selected.join(", ")).unwrap();
+    let matrix = resolve_matrix(args)
+        .iter()
+        .map(|(threads, loops)| format!("{} threads x {} loops", threads, loops))
+        .join(", ");
+    writeln!(plan, "matrix: {}", matrix).unwrap();
+    if args.ci {
+        writeln!(plan, "ci: loops sized to {:?} per benchmark", CI_BUDGET).unwrap();
+    }
+    writeln!(plan, "repeat: {}, warmup: {}", args.repeat, args.warmup).unwrap();
+    writeln!(plan, "seed: {:#x}, work: {}", args.seed, args.work).unwrap();
+    writeln!(
+        plan,
+        "timer: {}, pool: {}, raw: {}",
+        args.timer, args.pool, args.raw
+    )
+    .unwrap();
+    writeln!(plan, "format: {}, output: stdout", args.format).unwrap();
+    if let Some(path) = &args.log_file {
+        writeln!(plan, "log file: {} (appended)", path).unwrap();
+    }
+    if let Some(path) = &args.save_baseline {
+        writeln!(plan, "save baseline: {}", path).unwrap();
+    }
+    if let Some(path) = &args.baseline {
+        writeln!(plan, "compare against: {} (p < {})", path, args.p_value).unwrap();
+    }
+    Ok(plan)
+}
+
+// The resolved plan of `--log-file`: what `dry_run` describes, as JSON.
+fn plan_json(args: &Args) -> Json {
+    let benchmarks = args.benchmarks.clone().unwrap_or_else(benchmark_names);
+    let matrix = resolve_matrix(args)
+        .into_iter()
+        .map(|(threads, loops)| {
+            Json::object([
+                ("threads", Json::from(threads as u64)),
+                ("loops", Json::from(loops as u64)),
+            ])
+        })
+        .collect();
+    Json::object([
+        ("mode", Json::from(mode(args).as_str())),
+        (
+            "benchmarks",
+            Json::Array(benchmarks.iter().map(|n| Json::from(n.as_str())).collect()),
+        ),
+        ("matrix", Json::Array(matrix)),
+        ("repeat", Json::from(args.repeat as u64)),
+        ("warmup", Json::from(args.warmup as u64)),
+        ("seed", Json::from(args.seed)),
+        ("work", Json::from(args.work as u64)),
+        ("timer", Json::from(args.timer.to_string().as_str())),
+        ("pool", Json::from(args.pool)),
+        ("raw", Json::from(args.raw)),
+        ("ci", Json::from(args.ci)),
+        ("format", Json::from(args.format.to_string().as_str())),
+    ])
+}
+
+// Checks that `path` can be written without truncating an existing file.
+fn writable(path: &str) -> Result<(), String> {
+    let exists = std::path::Path::new(path).exists();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    if !exists {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if args.list {
+        print!("{}", list());
+        return;
+    }
+
+    if args.dry_run {
+        match dry_run(&args) {
+            Ok(plan) => print!("{}", plan),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    let mut log = match args.log_file.as_deref().map(RunLog::open).transpose() {
+        Ok(log) => log,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(log) = &mut log {
+        log.plan(plan_json(&args));
+    }
+
+    if args.timer != Timer::Instant {
+        eprintln!("{}", timer::init(args.timer));
+    }
+
+    #[cfg(feature = "numa")]
+    if let Some(policy) = args.numa {
+        eprintln!("{}", numa::init(policy));
+    }
+
+    let executor: Box<dyn Executor> = if args.pool {
+        Box::new(Pool::default())
+    } else {
+        Box::new(Spawn)
+    };
+    let executor = executor.as_ref();
+
+    if args.stress {
+        SEQUENCE_GLOBAL.store(0, Ordering::Relaxed);
+        stress(executor, "thread-local", 8, args.stress_loops, || {
+            SEQUENCER.with(|s| s.borrow_mut().inc())
+        });
+        let shared = Arc::new(LockFreeSequencer::new(128));
+        stress(executor, "lock-free", 8, args.stress_loops, move || {
+            shared.inc()
         });
-        benchmark("sequencer(64,16)", threads, loops, move || {
-            sequencer(loops, 64, 16)
+        return;
+    }
+
+    if args.verify {
+        match verify(executor, 8, args.stress_loops) {
+            Ok(n) => println!("verify: {} unique sequences match the atomic counter", n),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.churn {
+        let (leaked, _) = churn(executor, 8, args.stress_loops, args.seed, false);
+        let (reclaimed, _) = churn(executor, 8, args.stress_loops, args.seed, true);
+        assert!(
+            reclaimed < leaked,
+            "reclamation did not lower the global head"
+        );
+        println!(
+            "churn, seed {:#x}: 8 threads x {} sequencers: global head {} reclaimed, {} without ({:.1}% saved)",
+            args.seed,
+            args.stress_loops,
+            reclaimed,
+            leaked,
+            100.0 * (leaked - reclaimed) as f64 / leaked as f64
+        );
+        return;
+    }
+
+    if let Some(name) = &args.scan {
+        match scan_benchmark(executor, name, MATRIX[0].1) {
+            Ok((threads, ops)) => println!(
+                "throughput peaked at {} threads: {:.2} Mops/s",
+                threads,
+                ops / 1e6
+            ),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    if args.shards {
+        let loops = args.loops.unwrap_or(MATRIX[0].1);
+        let sweep = shard_sweep(executor, loops);
+        println!("{:>6} {:>10} {:>10}", "shards", "ns/iter", "Mops/s");
+        for (shards, result) in &sweep {
+            let ops = SHARD_THREADS as f64 * 1e3 / result.ns_per_iter;
+            println!("{:>6} {:>10.2} {:>10.2}", shards, result.ns_per_iter, ops);
+        }
+        let (best, _) = sweep
+            .iter()
+            .min_by(|a, b| a.1.ns_per_iter.total_cmp(&b.1.ns_per_iter))
+            .unwrap();
+        println!("best throughput with {} shards", best);
+        return;
+    }
+
+    if args.head_to_head {
+        let threads = resolve_matrix(&args).iter().map(|&(t, _)| t).collect_vec();
+        println!(
+            "{:>7} {:>14} {:>14} {:>14}",
+            "threads",
+            "thread-local",
+            "lock-free",
+            format!("sharded({})", HEAD_TO_HEAD_SHARDS)
+        );
+        for row in head_to_head(executor, &threads, args.stress_loops) {
+            println!(
+                "{:>7} {:>12.2}ns {:>12.2}ns {:>12.2}ns",
+                row[0].threads, row[0].ns_per_iter, row[1].ns_per_iter, row[2].ns_per_iter
+            );
+        }
+        return;
+    }
+
+    if let Some(params) = &args.mixed {
+        for (threads, loops) in resolve_matrix(&args) {
+            let result = mixed(executor, threads, loops, params);
+            if let Some(log) = &mut log {
+                log.result(&result);
+            }
+            println!("{}", result);
+        }
+        return;
+    }
+
+    if args.global_churn {
+        for (threads, _) in resolve_matrix(&args) {
+            for churn in [false, true] {
+                let (result, taken) = global_churn(executor, threads, args.stress_loops, churn);
+                if let Some(log) = &mut log {
+                    log.result(&result);
+                }
+                if churn {
+                    println!("{} churner windows: {}", result, taken);
+                } else {
+                    println!("{}", result);
+                }
+            }
+        }
+        return;
+    }
+
+    if let Some(only) = &args.benchmarks {
+        let names = benchmark_names();
+        if let Some(unknown) = only.iter().find(|name| !names.contains(name)) {
+            eprintln!("unknown benchmark: {}", unknown);
+            std::process::exit(2);
+        }
+    }
+
+    let matrix = resolve_matrix(&args);
+
+    if args.heatmap {
+        let heatmap = heatmap(executor, &HEATMAP_STEPS, &matrix, |step, result| {
+            if let Some(log) = &mut log {
+                log.result(result);
+            }
+            if args.format == Format::Jsonl {
+                let mut line = to_json_line(result);
+                line.push("step", Json::from(step));
+                line.push("lag_amp", Json::from(HEATMAP_LAG_AMP));
+                println!("{}", line.render(false));
+            }
         });
-        benchmark("sequencer(64,32)", threads, loops, move || {
-            sequencer(loops, 64, 32)
+        if args.format != Format::Jsonl {
+            print!("{}", heatmap);
+        }
+        return;
+    }
+
+    let only = args.benchmarks.as_deref();
+    let budget = args.ci.then_some(CI_BUDGET);
+    let mut overheads = HashMap::new();
+    let mut all = vec![];
+    let start = Instant::now();
+    let head = SEQUENCE_GLOBAL.load(Ordering::Relaxed);
+    // `--quiet` prints it with the final table instead.
+    if args.format == Format::Text && !args.quiet {
+        println!("seed: {:#x}", args.seed);
+    }
+    for repeat in 0..args.repeat {
+        for &(threads, loops) in &matrix {
+            if repeat == 0 {
+                for _ in 0..args.warmup {
+                    run(executor, threads, loops, budget, args.work, only, drop);
+                }
+            }
+            let overhead = if args.raw {
+                0.0
+            } else {
+                *overheads
+                    .entry(loops)
+                    .or_insert_with(|| loop_overhead(loops))
+            };
+            let mut results = vec![];
+            run(
+                executor,
+                threads,
+                loops,
+                budget,
+                args.work,
+                only,
+                |mut result| {
+                    result.subtract_overhead(overhead);
+                    // Streamed right away, so that long runs can be followed as they go.
+                    if args.format == Format::Jsonl {
+                        println!("{}", to_json_line(&result).render(false));
+                    }
+                    if let Some(log) = &mut log {
+                        log.result(&result);
+                    }
+                    results.push(result);
+                },
+            );
+            sort_results(&mut results);
+            if let Some(warning) = cold_not_slower(&results) {
+                eprintln!("warning: {}", warning);
+            }
+            if args.format == Format::Text && !args.quiet {
+                print_results(&results);
+            }
+            all.extend(results);
+        }
+    }
+
+    match args.format {
+        Format::Text => {
+            if args.quiet {
+                println!("seed: {:#x}", args.seed);
+            }
+            if args.repeat > 1 {
+                print_summary(&all, args.repeat);
+            } else if args.quiet {
+                sort_results(&mut all);
+                print_results(&all);
+            }
+            if !args.quiet {
+                let space = SpaceUsage {
+                    start: head,
+                    consumed: SEQUENCE_GLOBAL.load(Ordering::Relaxed) - head,
+                    elapsed: start.elapsed(),
+                };
+                println!("\n{}", space);
+            }
+        }
+        Format::Json => println!("{}", to_json(&all).render(args.json_pretty)),
+        Format::Jsonl => {}
+    }
+
+    if let Some(log) = &mut log {
+        log.done(all.len());
+    }
+
+    if let Some(path) = &args.save_baseline {
+        if let Err(e) = baseline::save(path, &all) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Some(path) = &args.baseline {
+        let before = match baseline::load(path) {
+            Ok(before) => before,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        };
+        let regressions = baseline::compare(&before, &baseline::collect(&all), args.p_value);
+        if regressions > 0 {
+            eprintln!(
+                "{} significant regressions against {} (p < {})",
+                regressions, path, args.p_value
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Mutex, MutexGuard};
+
+    static GLOBAL: Mutex<()> = Mutex::new(());
+
+    /// Keeps the tests that move `SEQUENCE_GLOBAL` from running at the same time as those
+    /// that look at it.
+    fn global() -> MutexGuard<'static, ()> {
+        GLOBAL.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn result(name: &str, ns_per_iter: f64) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            threads: 1,
+            loops: 1,
+            ns_per_iter,
+            allocs: None,
+            migrations: None,
+            quantiles: None,
+            spawn_join: Duration::ZERO,
+            samples: vec![ns_per_iter],
+        }
+    }
+
+    fn run_names(executor: &dyn Executor, threads: &[usize], only: &[&str]) -> Vec<BenchResult> {
+        let only = only.iter().map(|name| name.to_string()).collect_vec();
+        let mut results = vec![];
+        for &threads in threads {
+            run(executor, threads, 100, None, 0, Some(&only), |r| {
+                results.push(r)
+            });
+        }
+        results
+    }
+
+    #[test]
+    fn runs_print_in_the_same_order() {
+        let lines = || {
+            let mut results = run_names(&Spawn, &[2, 1], &["primitive", "atomic", "coarse"]);
+            sort_results(&mut results);
+            results
+                .iter()
+                .map(|r| format!("{} {}", r.threads, r.name))
+                .collect_vec()
+        };
+        let first = lines();
+        assert_eq!(first, lines());
+        assert_eq!(
+            first,
+            [
+                "1 atomic",
+                "1 coarse",
+                "1 primitive",
+                "2 atomic",
+                "2 coarse",
+                "2 primitive"
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_measure_every_benchmark_each_time() {
+        let results = (0..3)
+            .flat_map(|_| run_names(&Spawn, &[1, 2], &["primitive", "atomic"]))
+            .collect_vec();
+        let summary = summarize(&results);
+        assert_eq!(summary.len(), 4);
+        assert!(summary.values().all(|ns| ns.len() == 3));
+    }
+
+    #[test]
+    fn heatmap_has_a_cell_per_step_and_thread_count() {
+        let _global = global();
+        let mut measured = 0;
+        let heatmap = heatmap(&Spawn, &[16, 64, 256], &[(1, 1000), (2, 1000)], |_, _| {
+            measured += 1
         });
-        benchmark("sequencer(128,8)", threads, loops, move || {
-            sequencer(loops, 128, 8)
+        assert_eq!(measured, 6);
+        assert_eq!(heatmap.threads, [1, 2]);
+        assert_eq!(heatmap.cells.len(), 3);
+        assert!(heatmap.cells.iter().all(|row| row.len() == 2));
+        // A header, the thread counts and a row per step, all as wide.
+        let grid = heatmap.to_string();
+        let lines = grid.lines().skip(1).collect_vec();
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|line| line.len() == 3 * 8));
+    }
+
+    #[test]
+    fn the_matrix_stays_within_the_cores() {
+        let threads = |cores, oversubscribe| {
+            matrix(cores, oversubscribe)
+                .into_iter()
+                .map(|(threads, _)| threads)
+                .collect_vec()
+        };
+        assert_eq!(threads(Some(6), false), [1, 4, 6]);
+        assert_eq!(threads(Some(1), false), [1]);
+        assert_eq!(threads(Some(64), false), [1, 4, 8, 16, 32]);
+        assert_eq!(threads(Some(6), true), [1, 4, 8, 16, 32]);
+        assert_eq!(threads(None, false), [1, 4, 8, 16, 32]);
+    }
+
+    #[test]
+    fn every_registered_benchmark_runs() {
+        let _global = global();
+        let mut names = vec![];
+        run(&Spawn, 1, 10, None, 0, None, |r| names.push(r.name));
+        assert_eq!(names, benchmark_names());
+    }
+
+    #[test]
+    fn scan_stops_at_the_plateau() {
+        // Throughput doubling up to 8 threads, then flat within a few percent.
+        let curve = |threads: usize| match threads {
+            1..=8 => threads as f64 * 100.0,
+            16 => 830.0,
+            _ => 2000.0,
+        };
+        let mut tried = vec![];
+        let peak = scan(
+            |threads| {
+                tried.push(threads);
+                curve(threads)
+            },
+            0.05,
+            256,
+        );
+        assert_eq!(peak, (8, 800.0));
+        assert_eq!(tried, [1, 2, 4, 8, 16]);
+
+        // Still improving at the last thread count allowed.
+        assert_eq!(scan(|threads| threads as f64, 0.05, 32).0, 32);
+        // A drop right away.
+        assert_eq!(scan(|threads| 1.0 / threads as f64, 0.05, 32), (1, 1.0));
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn sequencer_allocates_once_per_step() {
+        let _global = global();
+        let result = benchmark(&Spawn, "sequencer(128,16)", 1, 12_800, |_| {
+            sequencer(12_800, 128, 16, 0)
         });
-        benchmark("sequencer(128,16)", threads, loops, move || {
-            sequencer(loops, 128, 16)
+        // Nothing else moves the global, so the lag never trips.
+        assert_eq!(result.allocs, Some(100));
+    }
+
+    #[test]
+    fn spawn_and_join_are_reported_apart() {
+        let run = |threads| benchmark(&Spawn, "primitive", threads, 1000, |_| primitive(1000));
+        let result = run(2);
+        assert!(result.spawn_join > Duration::ZERO);
+        assert!(result.to_string().contains(" spawn/join: "));
+        assert!(!run(1).to_string().contains("spawn/join"));
+    }
+
+    #[test]
+    fn migrations_are_reported_where_they_can_be_seen() {
+        let supported = cfg!(all(feature = "migration", target_os = "linux"));
+        let result = benchmark(&Spawn, "primitive", 2, 1000, |_| primitive(1000));
+        assert_eq!(result.migrations.is_some(), supported);
+        assert_eq!(result.to_string().contains(" migrated: "), supported);
+        let json = result.to_json().render(false);
+        assert_eq!(json.contains("\"migrations\":"), supported);
+    }
+
+    #[test]
+    fn only_last_runs_the_largest_thread_count() {
+        let args = |line: &str| Args::parse(line.split_whitespace().map(String::from)).unwrap();
+        let matrix = resolve_matrix(&args("--only-last --threads 1,8,4 --loops 10"));
+        assert_eq!(matrix, [(8, 10)]);
+        assert_eq!(resolve_matrix(&args("--only-last")).len(), 1);
+
+        let threads = matrix.iter().map(|&(threads, _)| threads).collect_vec();
+        let results = run_names(&Spawn, &threads, &["primitive", "atomic"]);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.threads == 8));
+    }
+
+    #[test]
+    fn reclaiming_on_drop_keeps_the_head_down() {
+        let (reclaimed, _) = churn(&Spawn, 4, 1000, 7, true);
+        let (forgotten, _) = churn(&Spawn, 4, 1000, 7, false);
+        // Every forgotten sequencer took a window of its own, or two if it tripped the lag.
+        assert!(
+            forgotten >= 4 * 1000 * 128,
+            "{} without reclamation",
+            forgotten
+        );
+        assert!(
+            reclaimed < forgotten,
+            "{} with reclamation, {} without",
+            reclaimed,
+            forgotten
+        );
+    }
+
+    #[test]
+    fn churn_replays_from_its_seed() {
+        let run = |seed| churn(&Spawn, 4, 1000, seed, true);
+        let (_, taken) = run(7);
+        assert_eq!(run(7).1, taken);
+        assert_ne!(run(8).1, taken);
+        // Every thread drew its own stream, and took 1 to 16 sequences per sequencer.
+        assert!(taken.iter().all_unique(), "{:?}", taken);
+        assert!(taken.iter().all(|&n| (1000..=16_000).contains(&n)));
+
+        // Alone on the global, a thread hands back all it didn't take, so the head is
+        // replayed too.
+        let alone = |seed| churn(&Spawn, 1, 1000, seed, true);
+        let (head, taken) = alone(7);
+        assert_eq!(head, taken[0]);
+        assert_eq!(alone(7), (head, taken));
+    }
+
+    #[test]
+    fn too_fast_runs_are_scaled_up() {
+        let noop = auto_scaled(10, |loops| {
+            benchmark(&Spawn, "noop", 1, loops, |_| Duration::ZERO)
         });
-        benchmark("sequencer(128,32)", threads, loops, move || {
-            sequencer(loops, 128, 32)
+        assert_eq!(noop.loops, 10 * MAX_LOOP_SCALE);
+
+        // 1us per iter is measurable from 1000 loops on.
+        let mut tried = vec![];
+        let scaled = auto_scaled(10, |loops| {
+            tried.push(loops);
+            BenchResult {
+                loops,
+                ..result("slow", 1000.0)
+            }
         });
+        assert_eq!(tried, [10, 100, 1000]);
+        assert_eq!(scaled.loops, 1000);
+    }
+
+    #[test]
+    fn work_runs_its_rounds_between_ops() {
+        assert_eq!(bench::work(0), 0);
+        assert_eq!(bench::work(37), 37);
+
+        let a = Arc::new(AtomicUsize::new(0));
+        let bare = atomic(1000, a.clone(), 0);
+        let busy = atomic(1000, a.clone(), 500);
+        // Still one op per iteration, with the work in between.
+        assert_eq!(a.load(Ordering::Relaxed), 2000);
+        assert!(busy > bare * 10, "{:?} with work, {:?} without", busy, bare);
+    }
+
+    #[test]
+    fn list_names_every_benchmark() {
+        let list = list();
+        for name in [
+            "primitive",
+            "atomic static",
+            "sequencer(128,16)",
+            "shared cas(128)",
+        ] {
+            let line = list
+                .lines()
+                .find(|line| line.starts_with(&format!("{:20} ", name)));
+            assert!(
+                line.is_some_and(|line| line.len() > 21),
+                "no {} in\n{}",
+                name,
+                list
+            );
+        }
+        assert!(list.contains(PRIMITIVE_MONO));
+        assert_eq!(list.lines().count(), benchmark_names().len());
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn mixed_threads_cycle_through_their_params() {
+        let _global = global();
+        const LOOPS: usize = 16_384;
+        // Lags no other thread can trip, so every thread allocates once per own step.
+        let params = [
+            StepLag {
+                step: 16,
+                lag: 1 << 40,
+            },
+            StepLag {
+                step: 1024,
+                lag: 1 << 40,
+            },
+        ];
+        let result = mixed(&Spawn, 4, LOOPS, &params);
+        // Threads 0 and 2 on the first pair, 1 and 3 on the second.
+        assert_eq!(
+            result.allocs,
+            Some(2 * (LOOPS / 16) as u64 + 2 * (LOOPS / 1024) as u64)
+        );
+        // All on one pair, as a check that the count tells them apart.
+        let result = mixed(&Spawn, 4, LOOPS, &params[..1]);
+        assert_eq!(result.allocs, Some(4 * (LOOPS / 16) as u64));
+    }
+
+    #[test]
+    fn budgeted_runs_fill_the_budget() {
+        // A synthetic benchmark costing 40ns per iter, with no timing noise.
+        let mut tried = vec![];
+        let measure = |loops| {
+            tried.push(loops);
+            BenchResult {
+                loops,
+                ..result("synthetic", 40.0)
+            }
+        };
+        let run = budgeted(CI_BUDGET, measure);
+        // Calibrated once its timing loop reached a millisecond.
+        assert_eq!(tried, [1000, 10_000, 100_000, 5_000_000]);
+        let elapsed = Duration::from_secs_f64(run.ns_per_iter * run.loops as f64 / 1e9);
+        assert!(elapsed <= CI_BUDGET, "{:?} over the budget", elapsed);
+        assert!(
+            elapsed >= CI_BUDGET * 9 / 10,
+            "{:?} short of the budget",
+            elapsed
+        );
 
-        benchmark("coarse", threads, loops, move || coarse(loops));
+        // Too cheap to time, so at least the loops `auto_scaled` would have used.
+        assert_eq!(
+            budget_loops(CI_BUDGET, &result("free", 0.0)),
+            MAX_LOOP_SCALE
+        );
+    }
+
+    #[test]
+    fn the_log_has_the_plan_and_every_result() {
+        let path = std::env::temp_dir().join(format!("bench-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let line = format!(
+            "--only primitive --only atomic --threads 1,2 --log-file {}",
+            path
+        );
+        let args = Args::parse(line.split_whitespace().map(String::from)).unwrap();
+
+        // Two runs, as the log is appended to.
+        for _ in 0..2 {
+            let mut log = RunLog::open(path).unwrap();
+            log.plan(plan_json(&args));
+            let results = run_names(&Spawn, &[1, 2], &["primitive", "atomic"]);
+            results.iter().for_each(|result| log.result(result));
+            log.done(results.len());
+        }
+        let text = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let events = text
+            .lines()
+            .map(|line| {
+                assert!(line.contains("\"unix_ms\":"), "no timestamp: {}", line);
+                line.split('"').nth(3).unwrap()
+            })
+            .collect_vec();
+        let run = ["plan", "result", "result", "result", "result", "done"];
+        assert_eq!(events, [run, run].concat());
+        let plan = text.lines().next().unwrap();
+        assert!(
+            plan.contains("\"benchmarks\":[\"primitive\",\"atomic\"]"),
+            "{}",
+            plan
+        );
+        assert!(plan.contains("\"environment\":{"), "{}", plan);
+        assert!(text.contains("\"name\":\"atomic\""));
+    }
+
+    #[test]
+    fn cold_has_to_be_slower_than_hot() {
+        let run = |cold, hot| {
+            cold_not_slower(&[result("sequencer cold", cold), result("sequencer hot", hot)])
+        };
+        assert_eq!(run(20.0, 1.0), None);
+        assert!(run(1.0, 1.0).is_some());
+        assert!(run(0.5, 1.0).is_some());
+        assert_eq!(cold_not_slower(&[result("sequencer cold", 0.0)]), None);
+    }
+
+    #[test]
+    fn cold_is_slower_than_hot() {
+        let _global = global();
+        let cold = sequencer_cold(10_000, 128, 128 * 16);
+        let hot = sequencer_hot(10_000, 128, 128 * 16);
+        assert!(cold > hot, "cold {:?}, hot {:?}", cold, hot);
+    }
+
+    #[test]
+    fn atomic_static_counts_like_atomic() {
+        let a = Arc::new(AtomicUsize::new(0));
+        atomic(1000, a.clone(), 0);
+        let before = ATOMIC_STATIC.load(Ordering::Relaxed);
+        atomic(1000, &ATOMIC_STATIC, 0);
+        assert_eq!(
+            ATOMIC_STATIC.load(Ordering::Relaxed) - before,
+            a.load(Ordering::Relaxed)
+        );
     }
 }
 
@@ -0,0 +1,84 @@
+use std::{fs::OpenOptions, io, ops::Deref, os::fd::AsRawFd, path::Path, ptr::NonNull};
+
+use crate::sequence::{AtomicSequence, GlobalBackend, Sequence};
+
+/// A global counter in a memory-mapped file, shared by every process that maps the same
+/// path. Bind sequencers to it with `Sequencer::rebase_mmap`.
+///
+/// The file holds nothing but the counter. A missing or short file is extended with
+/// zeroes, which only ever grows it, so processes racing to initialize it all end up on
+/// the same counter starting at 0. Existing contents are kept, so the counter survives
+/// restarts for as long as the file does.
+pub struct MmapGlobal {
+    counter: NonNull<AtomicSequence>,
+}
+
+// SAFETY: the mapping is only accessed through the atomic.
+unsafe impl Send for MmapGlobal {}
+unsafe impl Sync for MmapGlobal {}
+
+const SIZE: usize = std::mem::size_of::<AtomicSequence>();
+
+impl MmapGlobal {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if file.metadata()?.len() < SIZE as u64 {
+            file.set_len(SIZE as u64)?;
+        }
+        // SAFETY: a fresh shared mapping of the first `SIZE` bytes, which the file now
+        // has. The mapping outlives `file`, as mappings do.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                SIZE,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        // Mappings are page aligned, which satisfies the atomic's alignment.
+        let counter = NonNull::new(ptr.cast()).expect("mmap returned null");
+        Ok(Self { counter })
+    }
+}
+
+impl Deref for MmapGlobal {
+    type Target = AtomicSequence;
+
+    fn deref(&self) -> &AtomicSequence {
+        // SAFETY: mapped and valid for as long as `self`; zeroes are a valid atomic.
+        unsafe { self.counter.as_ref() }
+    }
+}
+
+impl GlobalBackend for MmapGlobal {
+    fn allocate(&self, n: Sequence) -> Option<Sequence> {
+        GlobalBackend::allocate(&**self, n)
+    }
+
+    fn head(&self) -> Sequence {
+        GlobalBackend::head(&**self)
+    }
+
+    fn move_head(&self, current: Sequence, new: Sequence) -> bool {
+        GlobalBackend::move_head(&**self, current, new)
+    }
+}
+
+impl Drop for MmapGlobal {
+    fn drop(&mut self) {
+        // SAFETY: unmaps exactly what `open` mapped; no references outlive `self`.
+        unsafe {
+            libc::munmap(self.counter.as_ptr().cast(), SIZE);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{fmt, fs, str::FromStr, sync::OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumaPolicy {
+    /// Round-robin threads over the nodes, so that contenders sit on different sockets.
+    Spread,
+    /// Fill up one node before moving to the next, so that contenders share a socket.
+    Pack,
+}
+
+impl FromStr for NumaPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spread" => Ok(Self::Spread),
+            "pack" => Ok(Self::Pack),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for NumaPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spread => write!(f, "spread"),
+            Self::Pack => write!(f, "pack"),
+        }
+    }
+}
+
+/// CPUs in the order benchmark threads are pinned to them: thread `i` gets `cpus[i % len]`.
+static PLACEMENT: OnceLock<Vec<usize>> = OnceLock::new();
+
+/// Applies `policy` to every benchmark thread spawned afterwards and returns a line
+/// describing what was done. Single-node and unsupported systems leave threads unpinned.
+pub fn init(policy: NumaPolicy) -> String {
+    let nodes = nodes();
+    if nodes.len() < 2 {
+        return format!(
+            "numa: {} requested, no-op on {} node(s)",
+            policy,
+            nodes.len()
+        );
+    }
+    let cpus = match policy {
+        NumaPolicy::Pack => nodes.iter().flatten().copied().collect(),
+        NumaPolicy::Spread => {
+            let longest = nodes.iter().map(Vec::len).max().unwrap_or(0);
+            (0..longest)
+                .flat_map(|i| nodes.iter().filter_map(move |node| node.get(i).copied()))
+                .collect()
+        }
+    };
+    PLACEMENT
+        .set(cpus)
+        .expect("numa placement initialized twice");
+    format!("numa: {} across {} nodes", policy, nodes.len())
+}
+
+/// Pins the calling benchmark thread according to the policy given to `init`, if any.
+pub fn pin_current(thread: usize) {
+    if let Some(cpus) = PLACEMENT.get() {
+        pin(cpus[thread % cpus.len()]);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin(cpu: usize) {
+    // SAFETY: `set` is a plain bit set, initialized by `CPU_ZERO` before use.
+    unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin(_: usize) {}
+
+/// CPUs of each NUMA node, from sysfs.
+#[cfg(target_os = "linux")]
+fn nodes() -> Vec<Vec<usize>> {
+    let Ok(dir) = fs::read_dir("/sys/devices/system/node") else {
+        return vec![];
+    };
+    let mut nodes = dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let id = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()?;
+            let cpus = parse_cpulist(&fs::read_to_string(entry.path().join("cpulist")).ok()?)?;
+            Some((id, cpus))
+        })
+        .filter(|(_, cpus)| !cpus.is_empty())
+        .collect::<Vec<(usize, _)>>();
+    nodes.sort();
+    nodes.into_iter().map(|(_, cpus)| cpus).collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn nodes() -> Vec<Vec<usize>> {
+    vec![]
+}
+
+/// Parses the kernel's `0-3,8,10-11` list format.
+fn parse_cpulist(s: &str) -> Option<Vec<usize>> {
+    let mut cpus = vec![];
+    for part in s.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((lo, hi)) => cpus.extend(lo.parse::<usize>().ok()?..=hi.parse().ok()?),
+            None => cpus.push(part.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
@@ -0,0 +1,127 @@
+/// Streaming estimate of one quantile in constant memory, by the P² algorithm of Jain and
+/// Chlamtac: five markers track the minimum, the maximum, the quantile and the two midpoints
+/// between, and move along a parabola through their neighbours as samples come in.
+#[derive(Debug, Clone)]
+pub struct QuantileEstimator {
+    p: f64,
+    count: usize,
+    /// Marker heights, i.e. the estimated sample values.
+    heights: [f64; 5],
+    /// Actual and desired marker positions, and how far the desired ones move per sample.
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl QuantileEstimator {
+    /// An estimator of the `p` quantile, `0.0 <= p <= 1.0`.
+    pub fn new(p: f64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "quantile {} out of range", p);
+        Self {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired: [0.0, 2.0 * p, 4.0 * p, 2.0 + 2.0 * p, 4.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    pub fn push(&mut self, x: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        let q = &mut self.heights;
+        // The cell `k` the sample falls into, stretching the extremes if it is outside.
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < q[i + 1]).unwrap()
+        };
+        self.positions[k + 1..].iter_mut().for_each(|n| *n += 1.0);
+        for (desired, inc) in self.desired.iter_mut().zip(self.increments) {
+            *desired += inc;
+        }
+
+        let n = &mut self.positions;
+        for i in 1..4 {
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// The estimate so far; exact while fewer than five samples came in, `None` before any.
+    pub fn estimate(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            1..=4 => {
+                let mut seen = self.heights[..self.count].to_vec();
+                seen.sort_by(f64::total_cmp);
+                Some(seen[((seen.len() - 1) as f64 * self.p).round() as usize])
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    fn exact(sorted: &[f64], p: f64) -> f64 {
+        sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+    }
+
+    #[test]
+    fn estimates_track_the_exact_quantiles() {
+        let mut rng = XorShift::new(1);
+        let uniform = (0..100_000)
+            .map(|_| (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64)
+            .collect::<Vec<_>>();
+        // Long tailed, like latencies.
+        let exponential = uniform.iter().map(|u| -(1.0 - u).ln()).collect::<Vec<_>>();
+
+        for samples in [uniform, exponential] {
+            let mut sorted = samples.clone();
+            sorted.sort_by(f64::total_cmp);
+            for p in [0.5, 0.9, 0.99] {
+                let mut estimator = QuantileEstimator::new(p);
+                samples.iter().for_each(|&x| estimator.push(x));
+                let (estimate, exact) = (estimator.estimate().unwrap(), exact(&sorted, p));
+                assert!(
+                    (estimate - exact).abs() <= 0.02 * exact.max(1.0),
+                    "p{}: estimated {}, exactly {}",
+                    p * 100.0,
+                    estimate,
+                    exact
+                );
+            }
+        }
+        assert_eq!(QuantileEstimator::new(0.5).estimate(), None);
+    }
+}
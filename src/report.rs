@@ -0,0 +1,448 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::json::Json;
+use rust_benchmark_atomic::sequence::Sequence;
+
+/// Version of the `--format json` document. Bump it whenever the structure of the
+/// document changes, so that downstream parsers can reject what they don't understand.
+///
+/// 1: `{ schema_version, results: [{ name, threads, loops, ns_per_iter }] }`
+/// 2: `ns_per_iter` is fractional.
+/// 3: results carry `allocs` when built with the `stats` feature.
+/// 4: results carry `spawn_join_ns`.
+/// 5: results carry `migrations` when built with the `migration` feature on Linux.
+/// 6: `ns_per_iter` is net of the timing loop overhead, unless run with `--raw`.
+/// 7: results of benchmarks timing single iterations carry `p50_ns` and `p99_ns`.
+pub const SCHEMA_VERSION: u64 = 7;
+
+pub struct BenchResult {
+    pub name: String,
+    pub threads: usize,
+    pub loops: usize,
+    pub ns_per_iter: f64,
+    /// Total global `fetch_add`s across threads, for sequencer benchmarks with `stats`.
+    pub allocs: Option<u64>,
+    /// Threads that ran on another CPU at the end of their timing loop than at its start,
+    /// where the platform can tell.
+    pub migrations: Option<usize>,
+    /// p50 and p99 of single iterations of the slowest thread, for benchmarks timing each
+    /// iteration. Estimated in constant memory, and not net of the timing overhead.
+    pub quantiles: Option<(f64, f64)>,
+    /// Wall time of the run not spent in the longest timing loop: spawning, lining up at
+    /// the barrier and joining.
+    pub spawn_join: Duration,
+    /// Per iter cost of each thread on its own, for `--baseline` comparisons.
+    pub samples: Vec<f64>,
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:20} {} threads {} loops: {:.2}ns per iter",
+            self.name, self.threads, self.loops, self.ns_per_iter
+        )?;
+        if let Some(allocs) = self.allocs {
+            let iters = (self.threads * self.loops) as u64;
+            write!(
+                f,
+                " allocs: {} (1 per {} iters)",
+                allocs,
+                iters / allocs.max(1)
+            )?;
+        }
+        if let Some(migrations) = self.migrations {
+            write!(f, " migrated: {}/{}", migrations, self.threads)?;
+        }
+        if let Some((p50, p99)) = self.quantiles {
+            write!(f, " p50/p99: {:.0}/{:.0}ns", p50, p99)?;
+        }
+        if self.threads > 1 {
+            write!(f, " spawn/join: {:?}", self.spawn_join)?;
+        }
+        Ok(())
+    }
+}
+
+impl BenchResult {
+    /// Takes the cost of the bare timing loop off the result and its samples, clamping at
+    /// zero where noise made a benchmark look cheaper than the loop itself.
+    pub fn subtract_overhead(&mut self, overhead: f64) {
+        let net = |ns: f64| (ns - overhead).max(0.0);
+        self.ns_per_iter = net(self.ns_per_iter);
+        self.samples.iter_mut().for_each(|ns| *ns = net(*ns));
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("name", Json::from(self.name.as_str())),
+            ("threads", Json::from(self.threads as u64)),
+            ("loops", Json::from(self.loops as u64)),
+            ("ns_per_iter", Json::from(self.ns_per_iter)),
+            (
+                "spawn_join_ns",
+                Json::from(self.spawn_join.as_nanos() as u64),
+            ),
+        ];
+        if let Some(allocs) = self.allocs {
+            fields.push(("allocs", Json::from(allocs)));
+        }
+        if let Some(migrations) = self.migrations {
+            fields.push(("migrations", Json::from(migrations as u64)));
+        }
+        if let Some((p50, p99)) = self.quantiles {
+            fields.push(("p50_ns", Json::from(p50)));
+            fields.push(("p99_ns", Json::from(p99)));
+        }
+        Json::object(fields)
+    }
+}
+
+/// Divides as `f64` so that sub-nanosecond costs don't truncate to zero.
+pub fn ns_per_iter(total: Duration, threads: usize, loops: usize) -> f64 {
+    total.as_nanos() as f64 / threads as f64 / loops as f64
+}
+
+/// Sorts by `(threads, name)` so the output of two runs can be diffed line by line.
+pub fn sort_results(results: &mut [BenchResult]) {
+    results.sort_by(|a, b| (a.threads, &a.name).cmp(&(b.threads, &b.name)));
+}
+
+pub fn print_results(results: &[BenchResult]) {
+    let mut last = None;
+    for result in results.iter() {
+        if last != Some(result.threads) {
+            println!();
+            last = Some(result.threads);
+        }
+        println!("{}", result);
+    }
+}
+
+/// The per iter costs of every `(threads, name)` over all runs, each sorted.
+pub fn summarize(results: &[BenchResult]) -> BTreeMap<(usize, &str), Vec<f64>> {
+    let mut samples: BTreeMap<(usize, &str), Vec<f64>> = BTreeMap::new();
+    for result in results {
+        samples
+            .entry((result.threads, &result.name))
+            .or_default()
+            .push(result.ns_per_iter);
+    }
+    samples
+        .values_mut()
+        .for_each(|ns| ns.sort_by(f64::total_cmp));
+    samples
+}
+
+pub fn print_summary(results: &[BenchResult], runs: usize) {
+    println!();
+    println!("summary over {} runs (median / min / max per iter):", runs);
+    let mut last = None;
+    for ((threads, name), ns) in summarize(results) {
+        if last != Some(threads) {
+            println!();
+            last = Some(threads);
+        }
+        println!(
+            "{:20} {} threads: {:.2}ns / {:.2}ns / {:.2}ns",
+            name,
+            threads,
+            median(&ns),
+            ns[0],
+            ns[ns.len() - 1]
+        );
+    }
+}
+
+fn median(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+/// One result as a standalone `--format jsonl` line, carrying its own schema version.
+pub fn to_json_line(result: &BenchResult) -> Json {
+    let mut line = Json::object([("schema_version", Json::from(SCHEMA_VERSION))]);
+    if let Json::Object(fields) = result.to_json() {
+        fields.into_iter().for_each(|(k, v)| line.push(k, v));
+    }
+    line
+}
+
+pub fn to_json(results: &[BenchResult]) -> Json {
+    Json::object([
+        ("schema_version", Json::from(SCHEMA_VERSION)),
+        (
+            "results",
+            Json::Array(results.iter().map(BenchResult::to_json).collect()),
+        ),
+    ])
+}
+
+const SECS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0;
+
+/// How much of the sequence space a run used, against the wall time it took: `consumed`
+/// sequences moved the global from `start`. Printed after the benchmarks to show how
+/// harmless the sequences lost in window tails are, or how they would not be in 32 bits.
+pub struct SpaceUsage {
+    pub start: Sequence,
+    pub consumed: Sequence,
+    pub elapsed: Duration,
+}
+
+impl SpaceUsage {
+    pub fn per_sec(&self) -> f64 {
+        self.consumed as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Seconds until `remaining` sequences are used up at `per_sec`; `None` at a rate of 0.
+pub fn secs_until_exhausted(remaining: Sequence, per_sec: f64) -> Option<f64> {
+    (per_sec > 0.0).then(|| remaining as f64 / per_sec)
+}
+
+fn lifetime(secs: Option<f64>) -> String {
+    match secs {
+        None => "forever".to_string(),
+        Some(secs) if secs < 3600.0 => format!("{:.0}s", secs),
+        Some(secs) if secs < SECS_PER_YEAR => format!("{:.1} days", secs / 86400.0),
+        Some(secs) => format!("{:.1e} years", secs / SECS_PER_YEAR),
+    }
+}
+
+impl fmt::Display for SpaceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = self.start.saturating_add(self.consumed);
+        let per_sec = self.per_sec();
+        write!(
+            f,
+            "sequence space: {} used in {:.1?} ({:.2e}/s, {:.1e}% of u64); at this rate u64 lasts {}, u32 {}",
+            self.consumed,
+            self.elapsed,
+            per_sec,
+            100.0 * end as f64 / Sequence::MAX as f64,
+            lifetime(secs_until_exhausted(Sequence::MAX - end, per_sec)),
+            lifetime(secs_until_exhausted(u32::MAX as Sequence, per_sec)),
+        )
+    }
+}
+
+pub struct Heatmap {
+    pub steps: Vec<Sequence>,
+    pub threads: Vec<usize>,
+    pub lag_amp: Sequence,
+    /// `cells[row][col]` is the per iter cost of `steps[row]` at `threads[col]`.
+    pub cells: Vec<Vec<f64>>,
+}
+
+impl fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "sequencer ns per iter (rows: step, columns: threads, lag: {} * step)",
+            self.lag_amp
+        )?;
+        write!(f, "{:>8}", "step")?;
+        for threads in &self.threads {
+            write!(f, "{:>8}", threads)?;
+        }
+        writeln!(f)?;
+        for (step, row) in self.steps.iter().zip(&self.cells) {
+            write!(f, "{:>8}", step)?;
+            for cell in row {
+                write!(f, "{:>8.2}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Just enough of a JSON reader to check what `Json::render` writes: the value, and what
+    // is left after it.
+    fn parse(s: &str) -> Option<(Json, &str)> {
+        let s = s.trim_start();
+        let (open, rest) = s.split_at(s.len().min(1));
+        match open {
+            "{" | "[" => {
+                let close = if open == "{" { "}" } else { "]" };
+                let mut items = vec![];
+                let mut rest = rest.trim_start();
+                while !rest.starts_with(close) {
+                    if !items.is_empty() {
+                        rest = rest.strip_prefix(',')?;
+                    }
+                    let mut key = None;
+                    if open == "{" {
+                        let (Json::Str(k), after) = parse(rest)? else {
+                            return None;
+                        };
+                        key = Some(k);
+                        rest = after.trim_start().strip_prefix(':')?;
+                    }
+                    let (value, after) = parse(rest)?;
+                    items.push((key, value));
+                    rest = after.trim_start();
+                }
+                let rest = &rest[1..];
+                Some(if open == "{" {
+                    let fields = items.into_iter().map(|(k, v)| (k.unwrap(), v));
+                    (Json::Object(fields.collect()), rest)
+                } else {
+                    (
+                        Json::Array(items.into_iter().map(|(_, v)| v).collect()),
+                        rest,
+                    )
+                })
+            }
+            "\"" => {
+                let end = rest.find('"')?;
+                Some((Json::Str(rest[..end].to_string()), &rest[end + 1..]))
+            }
+            _ => {
+                let end = s
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                    .unwrap_or(s.len());
+                let value = match &s[..end] {
+                    "true" => Json::Bool(true),
+                    "false" => Json::Bool(false),
+                    v => v
+                        .parse()
+                        .map(Json::Int)
+                        .or_else(|_| v.parse().map(Json::Float))
+                        .ok()?,
+                };
+                Some((value, &s[end..]))
+            }
+        }
+    }
+
+    fn field<'a>(json: &'a Json, key: &str) -> Option<&'a Json> {
+        match json {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn result(name: &str, threads: usize, ns_per_iter: f64) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            threads,
+            loops: 1000,
+            ns_per_iter,
+            allocs: None,
+            migrations: None,
+            quantiles: None,
+            spawn_join: Duration::from_micros(10),
+            samples: vec![ns_per_iter; threads],
+        }
+    }
+
+    #[test]
+    fn sub_nanosecond_costs_survive() {
+        assert_eq!(ns_per_iter(Duration::from_nanos(34), 1, 100), 0.34);
+        assert_eq!(ns_per_iter(Duration::from_nanos(3), 4, 1000), 0.00075);
+        assert_eq!(
+            result("primitive", 1, 0.34).to_string(),
+            "primitive            1 threads 1000 loops: 0.34ns per iter"
+        );
+    }
+
+    #[test]
+    fn json_lines_stand_alone() {
+        let results = [result("atomic", 1, 1.5), result("sequencer hot", 4, 0.5)];
+        let mut lines = results.iter().map(to_json_line).collect::<Vec<_>>();
+        lines[1].push("step", Json::from(128));
+        let text = lines
+            .iter()
+            .map(|line| line.render(false) + "\n")
+            .collect::<String>();
+        for (line, r) in text.lines().zip(&results) {
+            let (parsed, rest) = parse(line).unwrap();
+            assert_eq!(rest, "");
+            assert!(matches!(
+                field(&parsed, "schema_version"),
+                Some(Json::Int(SCHEMA_VERSION))
+            ));
+            assert!(matches!(field(&parsed, "name"), Some(Json::Str(name)) if *name == r.name));
+            assert!(
+                matches!(field(&parsed, "threads"), Some(Json::Int(t)) if *t == r.threads as u64)
+            );
+        }
+        assert_eq!(text.lines().count(), 2);
+        assert!(matches!(
+            field(&parse(text.lines().nth(1).unwrap()).unwrap().0, "step"),
+            Some(Json::Int(128))
+        ));
+    }
+
+    #[test]
+    fn overhead_is_taken_off_down_to_zero() {
+        let mut r = result("atomic", 2, 5.0);
+        r.samples = vec![4.0, 6.0];
+        r.subtract_overhead(1.5);
+        assert_eq!(r.ns_per_iter, 3.5);
+        assert_eq!(r.samples, [2.5, 4.5]);
+        // Noise below the loop itself reads as free, not negative.
+        r.subtract_overhead(4.0);
+        assert_eq!(r.ns_per_iter, 0.0);
+        assert_eq!(r.samples, [0.0, 0.5]);
+    }
+
+    #[test]
+    fn exhaustion_is_projected_at_the_observed_rate() {
+        let usage = SpaceUsage {
+            start: 0,
+            consumed: 2_000_000_000,
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(usage.per_sec(), 1e9);
+        let secs = secs_until_exhausted(Sequence::MAX - 2_000_000_000, usage.per_sec()).unwrap();
+        assert!(
+            (secs / SECS_PER_YEAR - 584.5).abs() < 0.1,
+            "{} years",
+            secs / SECS_PER_YEAR
+        );
+        let printed = usage.to_string();
+        assert!(
+            printed.ends_with("u64 lasts 5.8e2 years, u32 4s"),
+            "{}",
+            printed
+        );
+
+        assert_eq!(secs_until_exhausted(86_400 * 3, 1.0), Some(259_200.0));
+        assert_eq!(lifetime(secs_until_exhausted(86_400 * 3, 1.0)), "3.0 days");
+        assert_eq!(secs_until_exhausted(Sequence::MAX, 0.0), None);
+        assert_eq!(lifetime(None), "forever");
+    }
+
+    #[test]
+    fn json_carries_the_schema_version() {
+        let json = to_json(&[result("atomic", 1, 1.5), result("sequencer hot", 2, 0.25)]);
+        for pretty in [false, true] {
+            let rendered = json.render(pretty);
+            let (parsed, rest) = parse(&rendered).unwrap();
+            assert_eq!(rest, "");
+            assert!(matches!(
+                field(&parsed, "schema_version"),
+                Some(Json::Int(SCHEMA_VERSION))
+            ));
+            let Some(Json::Array(results)) = field(&parsed, "results") else {
+                panic!("no results");
+            };
+            assert_eq!(results.len(), 2);
+            assert!(
+                matches!(field(&results[1], "ns_per_iter"), Some(Json::Float(ns)) if *ns == 0.25)
+            );
+        }
+        assert!(json.render(true).contains('\n'));
+        assert!(!json.render(false).contains('\n'));
+    }
+}
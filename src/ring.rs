@@ -0,0 +1,49 @@
+use crate::sequence::{Sequence, Sequencer};
+
+/// Slot indices of a ring buffer, drawn from a sequencer: sequence `s` maps to slot
+/// `s % capacity`. Producers with sequencers over the same global claim distinct slots of
+/// one lap, and each one's slots follow its sequences around the ring.
+///
+/// Sequencers never wrap past `Sequence::MAX` (the global refuses the window instead), so slot
+/// numbering stays continuous for any capacity, powers of two or not.
+pub struct RingIndexer {
+    sequencer: Sequencer,
+    capacity: Sequence,
+    /// `capacity - 1` if the capacity is a power of two, to replace the division.
+    mask: Option<Sequence>,
+}
+
+impl RingIndexer {
+    pub fn new(sequencer: Sequencer, capacity: usize) -> Self {
+        assert!(capacity > 0, "ring capacity must be at least 1");
+        let capacity = capacity as Sequence;
+        Self {
+            sequencer,
+            capacity,
+            mask: capacity.is_power_of_two().then(|| capacity - 1),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// The next slot; panics once the underlying sequencer is exhausted.
+    pub fn claim(&mut self) -> usize {
+        let seq = self.sequencer.inc();
+        self.slot(seq)
+    }
+
+    pub fn try_claim(&mut self) -> Option<usize> {
+        let seq = self.sequencer.try_inc()?;
+        Some(self.slot(seq))
+    }
+
+    fn slot(&self, seq: Sequence) -> usize {
+        // Below `capacity`, which came from a `usize`, so the cast is lossless.
+        match self.mask {
+            Some(mask) => (seq & mask) as usize,
+            None => (seq % self.capacity) as usize,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+/// The seed of the randomized benchmarks unless `--seed` overrides it.
+pub const DEFAULT_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// xorshift64: cheap enough for a timing loop, and the same stream for the same seed on
+/// every platform.
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        // All zeroes is a fixed point of the generator.
+        Self(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+
+    /// The generator of thread `index` in a run seeded with `seed`: distinct per thread,
+    /// the same across runs.
+    pub fn for_thread(seed: u64, index: usize) -> Self {
+        Self::new(seed ^ (index as u64 + 1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(seed: u64, index: usize) -> Vec<u64> {
+        let mut rng = XorShift::for_thread(seed, index);
+        (0..100).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_stream() {
+        assert_eq!(stream(42, 3), stream(42, 3));
+        assert_ne!(stream(42, 3), stream(42, 4));
+        assert_ne!(stream(42, 3), stream(43, 3));
+        // A seed whose sub-seed is all zeroes still moves.
+        assert_ne!(stream(1, 0)[0], stream(1, 0)[1]);
+    }
+}
@@ -1,63 +1,1506 @@
 use std::cell::RefCell;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem::ManuallyDrop;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+#[cfg(all(feature = "mmap", unix))]
+use crate::mmap::MmapGlobal;
 
 pub type Sequence = u64;
 pub type AtomicSequence = AtomicU64;
 
 pub static SEQUENCE_GLOBAL: AtomicSequence = AtomicSequence::new(0);
+/// The highest sequence committed so far; see `Sequencer::commit`. It starts at 0, so
+/// sequence 0 counts as committed before anyone commits it: writers that publish through
+/// the committed head start their sequences at 1, e.g. with `advance_global_to(1)`.
+pub static SEQUENCE_COMMITTED: AtomicSequence = AtomicSequence::new(0);
+/// Blocks given to `Sequencer::commit_range` ahead of the committed head, from their first
+/// sequence to their last, and how many there are, to be checked without the lock.
+static COMMIT_REORDER: Mutex<BTreeMap<Sequence, Sequence>> = Mutex::new(BTreeMap::new());
+static COMMIT_BUFFERED: AtomicUsize = AtomicUsize::new(0);
 
 thread_local! {
-    pub static SEQUENCER: RefCell<Sequencer> = RefCell::new(Sequencer::new(Sequencer::DEFAULT_STEP, Sequencer::DEFAULT_LAG));
+    pub static SEQUENCER: RefCell<Sequencer> = const { RefCell::new(Sequencer::new(Sequencer::DEFAULT_STEP, Sequencer::DEFAULT_LAG)) };
+}
+
+/// Writes the current global head, to be restored with `restore_global` after a restart.
+pub fn persist_global<W: Write>(mut w: W) -> io::Result<()> {
+    w.write_all(&SEQUENCE_GLOBAL.load(Ordering::Relaxed).to_le_bytes())?;
+    w.flush()
+}
+
+/// Sets the global head to a persisted value plus `margin` and returns the new head.
+///
+/// Windows keep being handed out after a snapshot is taken, so a crash can lose sequences
+/// issued past it. `margin` must cover everything that may be allocated between two
+/// snapshots: at the very least the largest `step` times the number of sequencers.
+pub fn restore_global<R: Read>(mut r: R, margin: Sequence) -> io::Result<Sequence> {
+    let mut buf = [0; std::mem::size_of::<Sequence>()];
+    r.read_exact(&mut buf)?;
+    let head = Sequence::from_le_bytes(buf)
+        .checked_add(margin)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "restored head overflows"))?;
+    SEQUENCE_GLOBAL.store(head, Ordering::Relaxed);
+    Ok(head)
+}
+
+/// Moves the global head forward to `target`; unlike `restore_global` it never moves it back.
+pub fn advance_global_to(target: Sequence) {
+    SEQUENCE_GLOBAL.fetch_max(target, Ordering::Relaxed);
+}
+
+/// Whether the committed head has reached `seq`, see `Sequencer::commit`. Data guarded by
+/// `seq` may be read once this is true: the acquire load pairs with the commit's release.
+pub fn try_wait_committed(seq: Sequence) -> bool {
+    SEQUENCE_COMMITTED.load(Ordering::Acquire) >= seq
+}
+
+/// Blocks until `try_wait_committed(seq)`: spins for a while, then yields between checks.
+pub fn wait_until_committed(seq: Sequence) {
+    let mut backoff = Backoff::default();
+    while !try_wait_committed(seq) {
+        backoff.snooze();
+    }
+}
+
+/// `wait_until_committed` giving up after `timeout`. Returns whether `seq` was committed.
+pub fn wait_until_committed_timeout(seq: Sequence, timeout: Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    let mut backoff = Backoff::default();
+    while !try_wait_committed(seq) {
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        backoff.snooze();
+    }
+    true
+}
+
+// Spins with exponentially more pause hints per round, then yields the CPU from round
+// `SPIN_ROUNDS` on, so a long wait doesn't burn a core.
+#[derive(Default)]
+struct Backoff {
+    round: u32,
+}
+
+impl Backoff {
+    const SPIN_ROUNDS: u32 = 7;
+
+    fn snooze(&mut self) {
+        if self.round < Self::SPIN_ROUNDS {
+            (0..1 << self.round).for_each(|_| std::hint::spin_loop());
+            self.round += 1;
+        } else {
+            std::thread::yield_now();
+        }
+    }
 }
 
-pub struct Sequencer {
+/// Maps the sequences a run actually emitted onto `0..n`, closing the gaps left by unused
+/// window tails, for callers that need dense IDs after the fact. The mapping is monotonic,
+/// so every thread's sequences stay in the order they were drawn.
+pub fn compact(emitted: &[Sequence]) -> HashMap<Sequence, Sequence> {
+    let mut sorted = emitted.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(new, old)| (old, new as Sequence))
+        .collect()
+}
+
+pub struct Sequencer<B: GlobalBackend = Global> {
+    /// Start of the current window; sequences in `[base, local)` have been handed out.
+    base: Sequence,
     local: Sequence,
     target: Sequence,
+    /// Start of the latest window or extension this sequencer reserved, for
+    /// `inc_with_boundary`; `Sequence::MAX`, which is never handed out, if there is none.
+    boundary: Sequence,
 
     step: Sequence,
     lag: Sequence,
+
+    /// A bounded sequencer only serves `[local, target)` and never touches the global.
+    bounded: bool,
+    /// Whether the window came from this sequencer's own allocation, and may be handed back.
+    owned: bool,
+    /// Like `bounded`, until `resume`.
+    paused: bool,
+    /// The lag `inc` checks: `lag`, or `NO_LAG` while bounded or paused, so that the fast
+    /// path tests one field instead of three.
+    lag_check: Sequence,
+    global: B,
+
+    /// Windows allocated ahead by `prefetch_next_window`, oldest first, each above the
+    /// current one; at most `depth` of them.
+    staged: VecDeque<Range<Sequence>>,
+    depth: usize,
+
+    /// The highest sequence passed to `commit_batched`, and how far of that the committed
+    /// head has been told.
+    commit_pending: Sequence,
+    commit_published: Sequence,
+
+    #[cfg(feature = "stats")]
+    stats: Stats,
+}
+
+/// Where a [`Sequencer`] allocates its windows from. All operations are relaxed:
+/// sequences have to be unique, not to order other memory.
+///
+/// The methods are named apart from those of the atomics, which they would otherwise
+/// shadow wherever the trait is in scope.
+pub trait GlobalBackend {
+    /// Moves the head forward by `n` and returns where it was, or `None`, leaving the head
+    /// alone, if that would take it past `Sequence::MAX`. The head never wraps, so a
+    /// sequence once handed out is never handed out again.
+    fn allocate(&self, n: Sequence) -> Option<Sequence>;
+
+    fn head(&self) -> Sequence;
+
+    /// Moves the head from `current` to `new` if it is still at `current`, to give back or
+    /// extend a window in place. Backends without it refuse, and windows are then never
+    /// reclaimed or extended.
+    fn move_head(&self, current: Sequence, new: Sequence) -> bool {
+        let _ = (current, new);
+        false
+    }
+}
+
+impl GlobalBackend for AtomicSequence {
+    // Out of line: the CAS loop inlined into `inc` would keep the window from staying in
+    // registers, and allocating is the slow path anyway.
+    #[inline(never)]
+    fn allocate(&self, n: Sequence) -> Option<Sequence> {
+        AtomicSequence::fetch_update(self, Ordering::Relaxed, Ordering::Relaxed, |head| {
+            head.checked_add(n)
+        })
+        .ok()
+    }
+
+    #[inline(always)]
+    fn head(&self) -> Sequence {
+        AtomicSequence::load(self, Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    fn move_head(&self, current: Sequence, new: Sequence) -> bool {
+        AtomicSequence::compare_exchange(self, current, new, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+impl<T: GlobalBackend + ?Sized> GlobalBackend for Arc<T> {
+    #[inline(always)]
+    fn allocate(&self, n: Sequence) -> Option<Sequence> {
+        (**self).allocate(n)
+    }
+
+    #[inline(always)]
+    fn head(&self) -> Sequence {
+        (**self).head()
+    }
+
+    #[inline(always)]
+    fn move_head(&self, current: Sequence, new: Sequence) -> bool {
+        (**self).move_head(current, new)
+    }
+}
+
+/// The default backend: `SEQUENCE_GLOBAL`, or the counter a sequencer was rebased onto.
+///
+/// A plain reference on the hot path, rather than a match over static and shared
+/// counters, which measurably slowed down `inc`.
+#[derive(Clone)]
+pub struct Global {
+    counter: &'static AtomicSequence,
+    /// Keeps `counter` alive when it isn't a static.
+    _owner: Option<Arc<dyn Send + Sync>>,
+}
+
+impl Global {
+    const fn of_static(counter: &'static AtomicSequence) -> Self {
+        Self {
+            counter,
+            _owner: None,
+        }
+    }
+
+    fn shared<T: Send + Sync + 'static>(owner: Arc<T>, counter: fn(&T) -> &AtomicSequence) -> Self {
+        // SAFETY: `counter` lives as long as `owner` does, which is as long as `Self`, and
+        // the reference never leaves it with the `'static` lifetime: every use goes through
+        // `&self`.
+        let counter = unsafe { &*(counter(&owner) as *const AtomicSequence) };
+        Self {
+            counter,
+            _owner: Some(owner),
+        }
+    }
+}
+
+impl GlobalBackend for Global {
+    #[inline(always)]
+    fn allocate(&self, n: Sequence) -> Option<Sequence> {
+        self.counter.allocate(n)
+    }
+
+    #[inline(always)]
+    fn head(&self) -> Sequence {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    fn move_head(&self, current: Sequence, new: Sequence) -> bool {
+        self.counter
+            .compare_exchange(current, new, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+#[cfg(feature = "stats")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of allocations attempted on the global counter.
+    pub allocs: u64,
+    /// Number of sequences handed out, less those taken back by `dec`.
+    pub incs: u64,
+    /// Number of windows abandoned because the global ran more than `lag` ahead.
+    pub lag_trips: u64,
+    /// Total size of all windows allocated.
+    pub reserved: u64,
+    /// Number of window extensions and reclaims that lost their CAS to another thread.
+    pub cas_failures: u64,
+    /// Number of `fetch_max`es `commit_batched` issued on the committed head.
+    pub commit_publishes: u64,
+}
+
+#[cfg(feature = "stats")]
+impl Stats {
+    const EMPTY: Self = Self {
+        allocs: 0,
+        incs: 0,
+        lag_trips: 0,
+        reserved: 0,
+        cas_failures: 0,
+        commit_publishes: 0,
+    };
+
+    /// Share of all reserved sequences that were handed out, over the sequencer's lifetime.
+    pub fn average_utilization(&self) -> f64 {
+        if self.reserved == 0 {
+            return 0.0;
+        }
+        self.incs as f64 / self.reserved as f64
+    }
+}
+
+/// A suggested configuration derived from a sequencer's [`Stats`].
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TuningHint {
+    pub step: Sequence,
+    pub lag: Sequence,
+    pub rationale: String,
 }
 
 impl Sequencer {
-    const DEFAULT_LAG: Sequence = Self::DEFAULT_STEP * 16;
-    const DEFAULT_STEP: Sequence = 128;
+    /// The lag of the thread-local `SEQUENCER`.
+    pub const DEFAULT_LAG: Sequence = Self::DEFAULT_STEP * 16;
+    /// The step of the thread-local `SEQUENCER`.
+    pub const DEFAULT_STEP: Sequence = 128;
+    /// A lag that disables the lag check, and the shared load it costs on every `inc`.
+    pub const NO_LAG: Sequence = Sequence::MAX;
+
+    pub const fn new_no_lag(step: Sequence) -> Self {
+        Self::new(step, Self::NO_LAG)
+    }
 
+    // With the check on `step`, the compiler would otherwise build sequencers out of line,
+    // which doubles the cost of short-lived ones.
+    #[inline(always)]
     pub const fn new(step: Sequence, lag: Sequence) -> Self {
+        Self::with_backend(Global::of_static(&SEQUENCE_GLOBAL), step, lag)
+    }
+
+    /// A sequencer whose `prefetch_next_window` keeps up to `depth` whole windows staged
+    /// behind the current one, so that a burst crosses that many window boundaries without
+    /// touching the global. Depth 0 is `new`, where prefetching extends the window in place.
+    /// The global head counts the staged windows, so `lag` has to cover them as well.
+    pub const fn new_buffered(step: Sequence, lag: Sequence, depth: usize) -> Self {
+        let mut sequencer = Self::new(step, lag);
+        sequencer.depth = depth;
+        sequencer
+    }
+
+    /// A sequencer with a `step` sized for the cores of this machine; see
+    /// [`Sequencer::auto_for`].
+    pub fn auto(lag: Sequence) -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::auto_for(cores, lag)
+    }
+
+    /// A sequencer with a `step` sized for `cores` threads contending on the global.
+    ///
+    /// In the step x threads heatmaps the cost per `inc` stops improving once the step is
+    /// about 64 sequences per contending thread, so the step is that, rounded up to a power
+    /// of two and kept between 64 and 8192.
+    pub fn auto_for(cores: usize, lag: Sequence) -> Self {
+        const PER_CORE: Sequence = 64;
+        const MAX_STEP: Sequence = 8192;
+        let step = (PER_CORE * cores.max(1) as Sequence)
+            .next_power_of_two()
+            .min(MAX_STEP);
+        Self::new(step, lag)
+    }
+
+    /// The smallest `step`, with a lag of 16 windows like the default, that keeps `threads`
+    /// threads each taking `ops_per_sec` sequences below `max_global_ops_per_sec`
+    /// allocations from the global. Each allocation serves `step` incs, so the global sees
+    /// `threads * ops_per_sec / step` of them per second.
+    pub fn plan(
+        threads: usize,
+        ops_per_sec: u64,
+        max_global_ops_per_sec: u64,
+    ) -> (Sequence, Sequence) {
+        assert!(
+            max_global_ops_per_sec > 0,
+            "a global budget of 0 allocations"
+        );
+        let demand = (threads as u64).saturating_mul(ops_per_sec);
+        let step = demand.div_ceil(max_global_ops_per_sec).max(1);
+        let lag = step.saturating_mul(Self::DEFAULT_LAG / Self::DEFAULT_STEP);
+        (step, lag)
+    }
+
+    /// Builds a sequencer with an exact internal state, e.g. from [`Sequencer::into_parts`]
+    /// on the other side of a channel or FFI boundary. The window is on the default global.
+    ///
+    /// Nothing tells whether the global issued the window or went on past it, so unlike
+    /// windows the sequencer allocates itself, it is never handed back by `reclaim` or on
+    /// drop; a made-up one would otherwise rewind the global over sequences already out.
+    pub fn from_parts(local: Sequence, target: Sequence, step: Sequence, lag: Sequence) -> Self {
+        assert!(local <= target, "local {} beyond target {}", local, target);
         Self {
+            base: local,
+            local,
+            target,
+            boundary: Sequence::MAX,
+            step,
+            lag,
+            bounded: false,
+            owned: false,
+            paused: false,
+            lag_check: lag,
+            global: Global::of_static(&SEQUENCE_GLOBAL),
+            staged: VecDeque::new(),
+            depth: 0,
+            commit_pending: 0,
+            commit_published: 0,
+            #[cfg(feature = "stats")]
+            stats: Stats::EMPTY,
+        }
+    }
+
+    /// A sequencer for `inc_with_timestamp`: makes sure a `coarsetime::Updater` keeps the
+    /// cached clock fresh, starting one on first use, and fails if the clock doesn't move.
+    pub fn timestamped(step: Sequence, lag: Sequence) -> Result<Self, ClockError> {
+        clock_updater()?;
+        Ok(Self::new(step, lag))
+    }
+
+    /// Binds the sequencer to `global` from now on. The rest of the current window
+    /// belongs to the old counter's space and is reclaimed there if possible, so the next
+    /// `inc` allocates from `global`. A bounded sequencer becomes an unbounded one.
+    pub fn rebase(&mut self, global: Arc<AtomicSequence>) {
+        self.rebind(Global::shared(global, |global| global));
+    }
+
+    /// `rebase` onto a counter shared with other processes.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn rebase_mmap(&mut self, global: Arc<MmapGlobal>) {
+        self.rebind(Global::shared(global, |global| global));
+    }
+
+    fn rebind(&mut self, global: Global) {
+        self.reclaim();
+        self.global = global;
+        self.bounded = false;
+        self.update_lag_check();
+        self.base = 0;
+        self.local = 0;
+        self.target = 0;
+        self.boundary = Sequence::MAX;
+    }
+}
+
+impl<B: GlobalBackend> Sequencer<B> {
+    /// A sequencer allocating its windows from `backend` instead of the process-wide
+    /// global.
+    #[inline(always)]
+    pub const fn with_backend(backend: B, step: Sequence, lag: Sequence) -> Self {
+        if step == 0 {
+            zero_step();
+        }
+        Self {
+            base: 0,
             local: 0,
             target: 0,
+            boundary: Sequence::MAX,
             step,
             lag,
+            bounded: false,
+            owned: true,
+            paused: false,
+            lag_check: lag,
+            global: backend,
+            staged: VecDeque::new(),
+            depth: 0,
+            commit_pending: 0,
+            commit_published: 0,
+            #[cfg(feature = "stats")]
+            stats: Stats::EMPTY,
+        }
+    }
+
+    /// Checks the state is one the sequencer can work from, e.g. after `from_parts`: the
+    /// window doesn't run backwards, `step` is non-zero and `lag` covers at least one step.
+    /// The window itself may be larger than `step`, after `reserve` or a prefetch.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if self.base > self.local || self.local > self.target {
+            return Err(format!(
+                "window out of order: base {}, local {}, target {}",
+                self.base, self.local, self.target
+            ));
+        }
+        let mut end = self.target;
+        for window in &self.staged {
+            if window.is_empty() || window.start < end {
+                return Err(format!("staged window {:?} not above {}", window, end));
+            }
+            end = window.end;
+        }
+        if self.step == 0 {
+            return Err("step is 0".to_string());
         }
+        if self.lag < self.step {
+            return Err(format!(
+                "lag {} is below step {}, every allocation would trip it",
+                self.lag, self.step
+            ));
+        }
+        Ok(())
+    }
+
+    /// Takes the sequencer apart into `(local, target, step, lag)` without reclaiming its
+    /// window, so [`Sequencer::from_parts`] can carry on where it stopped. Only the state is
+    /// kept: a rebased global, the bound of a forked range and staged windows are not; the
+    /// latter are reclaimed where possible.
+    pub fn into_parts(self) -> (Sequence, Sequence, Sequence, Sequence) {
+        let mut this = ManuallyDrop::new(self);
+        this.flush_commits();
+        this.reclaim_staged();
+        drop(std::mem::take(&mut this.staged));
+        // SAFETY: `this` is never used or dropped again, so the global is dropped once.
+        drop(unsafe { std::ptr::read(&this.global) });
+        (this.local, this.target, this.step, this.lag)
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Renders the stats and the global head in the Prometheus text exposition format.
+    /// Every inc that didn't allocate a window counts as a fast path hit.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_text(&self) -> String {
+        use std::fmt::Write;
+
+        let Stats {
+            allocs,
+            incs,
+            lag_trips,
+            ..
+        } = self.stats;
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, u64)]| {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+            for (labels, value) in samples {
+                writeln!(out, "{}{} {}", name, labels, value).unwrap();
+            }
+        };
+        metric(
+            "sequencer_allocs_total",
+            "counter",
+            "Windows allocated from the global counter.",
+            &[("", allocs)],
+        );
+        metric(
+            "sequencer_incs_total",
+            "counter",
+            "Sequences handed out.",
+            &[
+                ("{path=\"fast\"}", incs.saturating_sub(allocs)),
+                ("{path=\"slow\"}", incs.min(allocs)),
+            ],
+        );
+        metric(
+            "sequencer_lag_trips_total",
+            "counter",
+            "Windows abandoned because the global ran ahead by more than the lag.",
+            &[("", lag_trips)],
+        );
+        metric(
+            "sequencer_global_head",
+            "gauge",
+            "Next sequence the global counter will hand out.",
+            &[("", self.global())],
+        );
+        out
     }
 
     pub fn global(&self) -> Sequence {
-        SEQUENCE_GLOBAL.load(Ordering::Relaxed)
+        self.global.head()
     }
 
     pub fn local(&self) -> Sequence {
         self.local
     }
 
+    /// Hands the unused rest of the window back to the global, if nothing was allocated
+    /// after it: one CAS moving the head from `target` back to `local`, after the same for
+    /// every staged window, newest first. Returns whether the window was reclaimed. Runs on
+    /// drop.
+    pub fn reclaim(&mut self) -> bool {
+        self.reclaim_staged();
+        if self.bounded || !self.owned || self.local == self.target {
+            return false;
+        }
+        if !self.global.move_head(self.target, self.local) {
+            #[cfg(feature = "stats")]
+            {
+                self.stats.cas_failures += 1;
+            }
+            return false;
+        }
+        self.target = self.local;
+        true
+    }
+
+    // Hands staged windows back newest first, for as long as each still ends at the global
+    // head, and abandons the rest. Returns how many sequences went back.
+    fn reclaim_staged(&mut self) -> Sequence {
+        let mut returned = 0;
+        while let Some(window) = self.staged.pop_back() {
+            if !self.global.move_head(window.end, window.start) {
+                #[cfg(feature = "stats")]
+                {
+                    self.stats.cas_failures += 1;
+                }
+                self.staged.clear();
+                break;
+            }
+            returned += window.end - window.start;
+        }
+        returned
+    }
+
+    /// Empties the window for shutdown: reclaims the rest of it like `reclaim` and returns
+    /// `(consumed, returned)`, the sequences handed out from the window and those given back
+    /// to the global, staged windows included. What another thread has allocated past is
+    /// abandoned instead, so `consumed + returned` falls short of the windows by the waste.
+    pub fn drain_to_global(&mut self) -> (Sequence, Sequence) {
+        let consumed = self.local - self.base;
+        let unused = self.target - self.local;
+        let staged = self.reclaim_staged();
+        let returned = staged + if self.reclaim() { unused } else { 0 };
+        self.base = self.local;
+        self.target = self.local;
+        (consumed, returned)
+    }
+
+    /// Marks `seq` as finished, moving the committed head up to it. The head only ever
+    /// advances, so commits may arrive out of order; it tells how far writers have got,
+    /// not that every sequence below it was committed.
+    pub fn commit(&self, seq: Sequence) {
+        SEQUENCE_COMMITTED.fetch_max(seq, Ordering::Release);
+    }
+
+    /// `commit`, batched like allocations: the committed head is only moved once a `step`
+    /// of sequences has been committed since it last was, or on `flush_commits`. Readers
+    /// see a batch late, never early. Dropping the sequencer flushes.
+    pub fn commit_batched(&mut self, seq: Sequence) {
+        self.commit_pending = self.commit_pending.max(seq);
+        if self.commit_pending - self.commit_published >= self.step {
+            self.flush_commits();
+        }
+    }
+
+    /// Moves the committed head up to every sequence given to `commit_batched` so far.
+    pub fn flush_commits(&mut self) {
+        if self.commit_pending == self.commit_published {
+            return;
+        }
+        #[cfg(feature = "stats")]
+        {
+            self.stats.commit_publishes += 1;
+        }
+        SEQUENCE_COMMITTED.fetch_max(self.commit_pending, Ordering::Release);
+        self.commit_published = self.commit_pending;
+    }
+
+    /// `commit`, in allocation order: the committed head only moves on to `seq` once it is
+    /// at `seq - 1`, i.e. once every sequence below has been committed too. Commits ahead
+    /// of the head wait in a reorder buffer shared by all sequencers until the gap before
+    /// them fills.
+    pub fn commit_ordered(&self, seq: Sequence) {
+        self.commit_range(seq..seq.saturating_add(1));
+    }
+
+    /// `commit_ordered` for a whole block at once, such as a window handed out in one go:
+    /// the head moves to `range.end - 1` if the block starts right above it or lower, and
+    /// the block is buffered otherwise.
+    pub fn commit_range(&self, range: Range<Sequence>) {
+        if range.is_empty() {
+            return;
+        }
+        let last = range.end - 1;
+        let head = SEQUENCE_COMMITTED.load(Ordering::Acquire);
+        if last <= head {
+            return;
+        }
+        // `last > head`, so `head + 1` can't overflow.
+        if range.start <= head + 1 {
+            // The head only grows, so the block still joins it here. The lock is only
+            // needed if buffered blocks may join it now too.
+            SEQUENCE_COMMITTED.fetch_max(last, Ordering::SeqCst);
+            if COMMIT_BUFFERED.load(Ordering::SeqCst) > 0 {
+                drain_reorder(&mut COMMIT_REORDER.lock().unwrap());
+            }
+            return;
+        }
+        let mut pending = COMMIT_REORDER.lock().unwrap();
+        let buffered = pending.entry(range.start).or_insert(last);
+        *buffered = (*buffered).max(last);
+        COMMIT_BUFFERED.store(pending.len(), Ordering::SeqCst);
+        // A block joining the head since it was read above may have seen nothing buffered
+        // yet; this drain picks up what it would have. Both sides being `SeqCst`, one of
+        // them sees the other.
+        drain_reorder(&mut pending);
+    }
+
+    /// The highest sequence committed. Through ordered commits only, every sequence below
+    /// it has been committed as well; an unordered `commit` may have skipped over some.
+    pub fn committed_head(&self) -> Sequence {
+        SEQUENCE_COMMITTED.load(Ordering::Acquire)
+    }
+
+    pub fn remaining(&self) -> Sequence {
+        self.target - self.local
+    }
+
+    /// How many sequences the global can still hand out before it overflows, to alarm on
+    /// ahead of exhaustion. A bounded sequencer never draws from the global and counts what
+    /// is left of its range instead.
+    pub fn remaining_global_space(&self) -> Sequence {
+        if self.bounded {
+            return self.remaining();
+        }
+        Sequence::MAX - self.global()
+    }
+
+    /// Stops allocating from the global: until `resume`, the sequencer only serves what is
+    /// left of its current window, after which `try_inc` returns `None` and `inc` panics.
+    /// Staged windows wait untouched. Quiesces allocation without tearing down the threads.
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.update_lag_check();
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.update_lag_check();
+    }
+
+    fn update_lag_check(&mut self) {
+        self.lag_check = if self.bounded || self.paused {
+            Sequencer::NO_LAG
+        } else {
+            self.lag
+        };
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether a bounded sequencer has handed out its last sequence; unbounded ones never are.
+    pub fn is_exhausted(&self) -> bool {
+        self.bounded && self.local == self.target
+    }
+
+    /// The part of the reserved window not handed out yet.
+    pub fn local_window(&self) -> Range<Sequence> {
+        self.local..self.target
+    }
+
+    /// The `step` aligned range `seq` falls into. It matches the window `seq` was
+    /// allocated in as long as every sequencer on the global uses the same step and nothing
+    /// else moved the global off a multiple of it.
+    pub fn window_of(&self, seq: Sequence) -> Range<Sequence> {
+        let start = seq - seq % self.step;
+        start..start.saturating_add(self.step)
+    }
+
+    /// Fraction of the current window handed out so far. Windows abandoned at a low
+    /// utilization point at a `step` too large or a `lag` too tight.
+    pub fn window_utilization(&self) -> f64 {
+        if self.target == self.base {
+            return 0.0;
+        }
+        (self.local - self.base) as f64 / (self.target - self.base) as f64
+    }
+
+    // The staging paths make `inc` big enough that the compiler would otherwise call it out
+    // of line, and the window could no longer stay in registers between calls.
+    #[inline]
     pub fn inc(&mut self) -> Sequence {
-        self.try_alloc();
+        match self.try_inc() {
+            Some(seq) => seq,
+            None => exhausted(),
+        }
+    }
+
+    /// Like `inc`, also returning a timestamp in nanoseconds for latency tagging.
+    ///
+    /// The clock is `coarsetime::Instant::recent()`: `CLOCK_MONOTONIC_COARSE` on Linux, read
+    /// from a process-wide cache instead of the OS, so taking it costs one relaxed load. It is
+    /// monotonic, so timestamps never go backwards, but only advances when the cache is
+    /// refreshed by `coarsetime::Instant::now`, `Instant::update` or a `coarsetime::Updater`;
+    /// build the sequencer with `Sequencer::timestamped` to have one running.
+    pub fn inc_with_timestamp(&mut self) -> (Sequence, u64) {
+        let seq = self.inc();
+        let now = coarsetime::Instant::recent();
+        (
+            seq,
+            coarsetime::Duration::from_ticks(now.as_ticks()).as_nanos(),
+        )
+    }
+
+    /// Returns `None` instead of panicking once a bounded sequencer is exhausted or the
+    /// next window would overflow the sequence space.
+    #[inline]
+    pub fn try_inc(&mut self) -> Option<Sequence> {
+        if !self.try_alloc() {
+            return None;
+        }
+        // `try_alloc` guarantees `local < target`, so this cannot overflow.
+        Some(self.take())
+    }
+
+    /// Hands out `n` contiguous sequences and returns the first, or fails without touching
+    /// the global if they can't be had without passing `Sequence::MAX`.
+    pub fn inc_n_checked(&mut self, n: Sequence) -> Result<Sequence, OverflowError> {
+        let available = self.available();
+        if n > available || !self.reserve(n) {
+            // `available` is only a snapshot of the global; another thread may have
+            // taken what was left in the meantime.
+            return Err(OverflowError {
+                available: self.available(),
+            });
+        }
+        #[cfg(feature = "stats")]
+        {
+            self.stats.incs += n;
+        }
+        let res = self.local;
+        self.local += n;
+        Ok(res)
+    }
+
+    /// Fills `buf` with consecutive sequences from a single reservation, for batches without
+    /// allocating a `Vec`. Panics like `inc` if they can't be had.
+    pub fn inc_many_into(&mut self, buf: &mut [Sequence]) {
+        let Ok(first) = self.inc_n_checked(buf.len() as Sequence) else {
+            exhausted()
+        };
+        for (slot, seq) in buf.iter_mut().zip(first..) {
+            *slot = seq;
+        }
+    }
+
+    /// The largest `n` that `reserve` can currently satisfy without overflowing.
+    fn available(&self) -> Sequence {
+        if self.bounded || self.paused {
+            return self.remaining();
+        }
+        let space = Sequence::MAX - self.global.head();
+        if space < self.step {
+            // A fresh window would be at least `step` long and can no longer be had.
+            return self.remaining();
+        }
+        self.remaining().max(space)
+    }
+
+    /// Like `try_inc`, but never allocates: `None` as soon as the local window is used up or
+    /// the lag check trips, leaving it to the caller to `reserve` off the hot path.
+    pub fn try_inc_local(&mut self) -> Option<Sequence> {
+        if self.local == self.target || self.lagging() {
+            return None;
+        }
+        Some(self.take())
+    }
+
+    /// Makes sure the next `n` sequences come from the local window, replacing the window
+    /// with a fresh one of at least `n` if needed. Returns `false` if a bounded sequencer
+    /// lacks the space or the allocation would overflow.
+    pub fn reserve(&mut self, n: Sequence) -> bool {
+        if self.remaining() >= n {
+            return true;
+        }
+        !self.bounded && !self.paused && self.alloc_window(n.max(self.step))
+    }
+
+    /// Extends the window by another `step` while it still has a few sequences left, so that
+    /// the next allocation happens ahead of the `inc` that would need it. Only done if the
+    /// window still ends at the global head, as the extension has to be contiguous;
+    /// otherwise, and for bounded sequencers, this is a no-op. Returns whether it extended.
+    ///
+    /// With a `depth` from [`Sequencer::new_buffered`], it instead allocates whole windows
+    /// of `step` until `depth` of them are staged, whatever is left of the current one, and
+    /// returns whether it staged any. `inc` moves on to them in order.
+    #[inline]
+    pub fn prefetch_next_window(&mut self) -> bool {
+        if self.depth > 0 {
+            return self.stage_windows();
+        }
+        // Windows smaller than 8 prefetch once they are down to their last sequence.
+        let threshold = (self.step / 8).max(1);
+        if self.bounded || self.paused || self.remaining() > threshold || self.local == self.target
+        {
+            return false;
+        }
+        let Some(target) = self.target.checked_add(self.step) else {
+            return false;
+        };
+        if !self.global.move_head(self.target, target) {
+            #[cfg(feature = "stats")]
+            {
+                self.stats.cas_failures += 1;
+            }
+            return false;
+        }
+        #[cfg(feature = "stats")]
+        {
+            self.stats.allocs += 1;
+            self.stats.reserved += self.step;
+        }
+        // Only a window of a sequence or two can be extended before its first sequence is
+        // out; that start is what stays marked then.
+        if self.boundary == Sequence::MAX || self.boundary < self.local {
+            self.boundary = self.target;
+        }
+        self.target = target;
+        true
+    }
+
+    fn stage_windows(&mut self) -> bool {
+        if self.bounded || self.paused {
+            return false;
+        }
+        let mut staged = false;
+        while self.staged.len() < self.depth {
+            #[cfg(feature = "stats")]
+            {
+                self.stats.allocs += 1;
+            }
+            let Some(start) = self.global.allocate(self.step) else {
+                break;
+            };
+            let end = start + self.step;
+            #[cfg(feature = "stats")]
+            {
+                self.stats.reserved += self.step;
+            }
+            self.staged.push_back(start..end);
+            staged = true;
+        }
+        staged
+    }
+
+    /// The lag check of `inc`, run on demand: if the global has moved more than `lag` past
+    /// `local`, abandons the window for a fresh one at the head. Meant for sequencers built
+    /// with `NO_LAG`, which keep `inc` free of the shared load and catch up only when the
+    /// caller asks; for those, falling more than a `step` behind counts.
+    pub fn fast_forward_if_behind(&mut self) {
+        if self.bounded || self.paused || self.local == self.target {
+            return;
+        }
+        let lag = if self.lag == Sequencer::NO_LAG {
+            self.step
+        } else {
+            self.lag
+        };
+        if self.local.saturating_add(lag) < self.global.head() {
+            #[cfg(feature = "stats")]
+            {
+                self.stats.lag_trips += 1;
+            }
+            self.alloc_window(self.step);
+        }
+    }
+
+    /// Like `reserve`, but settles for less: returns how many of the next `n` sequences are
+    /// now in the local window, which is at most what a bounded sequencer has left.
+    pub fn saturating_reserve(&mut self, n: Sequence) -> Sequence {
+        if self.reserve(n) {
+            return n;
+        }
+        self.remaining().min(n)
+    }
+
+    /// # Safety
+    ///
+    /// The caller must guarantee `remaining() > 0`, e.g. with `reserve`. No allocation or
+    /// lag check is performed, so misuse hands out sequences outside the window.
+    pub unsafe fn inc_unchecked(&mut self) -> Sequence {
+        debug_assert!(self.local < self.target, "inc_unchecked without budget");
+        self.take()
+    }
+
+    /// Like `inc`, also telling whether the sequence is the first of a reservation: a new
+    /// window, whether allocated by this call or an earlier `reserve` or `inc_n_checked`, a
+    /// staged window, or a prefetch extension. Taking it back with `dec` and handing it out again doesn't
+    /// count twice, and neither do windows the sequencer didn't reserve itself, such as
+    /// those of `fork_range` children and `from_parts`.
+    pub fn inc_with_boundary(&mut self) -> (Sequence, bool) {
+        let seq = self.inc();
+        (seq, seq == self.boundary)
+    }
+
+    /// Takes back the sequence handed out last, for LIFO reuse. Only works within the current
+    /// window: `None` once `local` is back at the window base.
+    pub fn dec(&mut self) -> Option<Sequence> {
+        if self.local == self.base {
+            return None;
+        }
+        #[cfg(feature = "stats")]
+        {
+            // A sequence `skip` passed over was never counted.
+            self.stats.incs = self.stats.incs.saturating_sub(1);
+        }
+        self.local -= 1;
+        if self.local == self.boundary {
+            self.boundary = Sequence::MAX;
+        }
+        Some(self.local)
+    }
+
+    /// Consumes the next `n` sequences without returning them.
+    pub fn skip(&mut self, mut n: Sequence) {
+        while n > 0 {
+            if !self.try_alloc() {
+                exhausted();
+            }
+            let k = n.min(self.remaining());
+            self.local += k;
+            n -= k;
+        }
+    }
+
+    /// Consumes sequences until the next one returned by `inc` is a multiple of `m`.
+    pub fn align_to(&mut self, m: Sequence) {
+        assert!(m > 0, "alignment must be non-zero");
+        loop {
+            if !self.try_alloc() {
+                exhausted();
+            }
+            match self.local.checked_next_multiple_of(m) {
+                Some(next) if next < self.target => {
+                    self.local = next;
+                    return;
+                }
+                Some(_) => self.local = self.target,
+                None => exhausted(),
+            }
+        }
+    }
+
+    pub fn next_multiple_of(&mut self, m: Sequence) -> Sequence {
+        self.align_to(m);
+        // Take the aligned sequence directly: going through `inc` could trip the lag check
+        // and move to an unaligned window.
+        self.take()
+    }
+
+    /// How contended the global looks from here, between 0.0 and 1.0: the share of trips to
+    /// the global that were caused or wasted by other threads, i.e. lag trips and lost CASes
+    /// out of all allocations and CAS attempts. A caller seeing it rise may want a larger
+    /// step. Derived from the stats only, so it costs nothing on the hot path.
+    #[cfg(feature = "stats")]
+    pub fn contention_estimate(&self) -> f64 {
+        let Stats {
+            allocs,
+            lag_trips,
+            cas_failures,
+            ..
+        } = self.stats;
+        let attempts = allocs + cas_failures;
+        if attempts == 0 {
+            return 0.0;
+        }
+        (lag_trips + cas_failures) as f64 / attempts as f64
+    }
+
+    /// Suggests a `step`/`lag` pair from the stats gathered so far: a larger step when
+    /// allocations are frequent, a smaller lag when windows are often abandoned.
+    #[cfg(feature = "stats")]
+    pub fn tuning_hint(&self) -> TuningHint {
+        // Below this many sequences per `fetch_add` the global counter becomes the bottleneck.
+        const INCS_PER_ALLOC: u64 = 64;
+        // Above this share of allocations caused by lag trips, threads fall behind too often.
+        const LAG_TRIP_PERCENT: u64 = 25;
+
+        let Stats {
+            allocs,
+            incs,
+            lag_trips,
+            ..
+        } = self.stats;
+        let mut hint = TuningHint {
+            step: self.step,
+            lag: self.lag,
+            rationale: String::new(),
+        };
+        let mut reasons = vec![];
+        if allocs > 0 && incs / allocs < INCS_PER_ALLOC {
+            let per_alloc = (incs / allocs).max(1);
+            hint.step = (self.step.saturating_mul(INCS_PER_ALLOC) / per_alloc)
+                .checked_next_power_of_two()
+                .unwrap_or(Sequence::MAX)
+                .max(self.step.saturating_mul(2));
+            reasons.push(format!(
+                "allocs dominate: {} allocs for {} incs, raise step to {}",
+                allocs, incs, hint.step
+            ));
+        }
+        if allocs > 0 && lag_trips * 100 > allocs * LAG_TRIP_PERCENT {
+            // Catching up earlier keeps a lagging thread closer to the global,
+            // but a lag below one window would trip on every allocation.
+            hint.lag = (self.lag / 2).max(hint.step);
+            reasons.push(format!(
+                "threads fall behind: {} of {} allocs were lag trips, lower lag to {}",
+                lag_trips, allocs, hint.lag
+            ));
+        }
+        hint.rationale = if reasons.is_empty() {
+            "current step and lag look fine".to_string()
+        } else {
+            reasons.join("; ")
+        };
+        hint
+    }
+
+    /// Hands out `local`; the caller has made sure it lies inside the window.
+    #[inline(always)]
+    fn take(&mut self) -> Sequence {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.incs += 1;
+        }
         let res = self.local;
         self.local += 1;
         res
     }
 
     #[inline(always)]
-    fn try_alloc(&mut self) {
-        if self.local == self.target
-            || self.local + self.lag < SEQUENCE_GLOBAL.load(Ordering::Relaxed)
-        {
-            self.alloc()
+    fn try_alloc(&mut self) -> bool {
+        if self.local == self.target {
+            return self.alloc();
         }
+        if self.lagging() {
+            #[cfg(feature = "stats")]
+            {
+                self.stats.lag_trips += 1;
+            }
+            // Staged windows are behind the head as well, and go with the fresh window. If
+            // there is none to be had, they are still served after the current one.
+            return self.alloc_window(self.step) || self.local < self.target;
+        }
+        true
+    }
+
+    // Bounded sequencers never look at the global, and a paused one keeps serving its
+    // window, lagging or not.
+    #[inline(always)]
+    fn lagging(&self) -> bool {
+        // `NO_LAG` keeps the fast path a purely local comparison. Saturate so that other
+        // lags near `Sequence::MAX` simply never trip.
+        self.lag_check != Sequencer::NO_LAG
+            && self.local.saturating_add(self.lag_check) < self.global.head()
+    }
+
+    // Overflow policy: sequences never wrap. A window whose end would pass `Sequence::MAX`
+    // is refused by the global, which stays where it is, and the current window (if any)
+    // keeps serving. Every later allocation of that size is refused as well.
+    #[inline(always)]
+    fn alloc(&mut self) -> bool {
+        if self.bounded || self.paused {
+            return self.local < self.target;
+        }
+        if let Some(window) = next_staged(&mut self.staged) {
+            self.base = window.start;
+            self.local = window.start;
+            self.target = window.end;
+            self.boundary = window.start;
+            return true;
+        }
+        self.alloc_window(self.step) || self.local < self.target
     }
 
     #[inline(always)]
-    fn alloc(&mut self) {
-        self.local = SEQUENCE_GLOBAL.fetch_add(self.step, Ordering::Relaxed);
-        self.target = self.local + self.step;
+    fn alloc_window(&mut self, n: Sequence) -> bool {
+        // Only a `from_parts` state can have a `step` of 0; an empty window would leave
+        // `local == target` and `take` would hand out sequences past it.
+        if n == 0 {
+            return false;
+        }
+        #[cfg(feature = "stats")]
+        {
+            self.stats.allocs += 1;
+        }
+        let Some(local) = self.global.allocate(n) else {
+            return false;
+        };
+        // The new window is above every staged one, which it replaces.
+        self.staged.clear();
+        #[cfg(feature = "stats")]
+        {
+            self.stats.reserved += n;
+        }
+        self.base = local;
+        self.local = local;
+        self.target = local + n;
+        self.boundary = local;
+        self.owned = true;
+        true
+    }
+}
+
+// Children share the parent's backend.
+impl<B: GlobalBackend + Clone> Sequencer<B> {
+    /// Hands `[local, local + n)` to a bounded child and advances past it.
+    pub fn fork_range(&mut self, n: Sequence) -> Option<Self> {
+        if self.remaining() < n {
+            return None;
+        }
+        let child = Self {
+            base: self.local,
+            local: self.local,
+            target: self.local + n,
+            boundary: Sequence::MAX,
+            step: self.step,
+            lag: self.lag,
+            bounded: true,
+            owned: false,
+            paused: false,
+            lag_check: Sequencer::NO_LAG,
+            global: self.global.clone(),
+            staged: VecDeque::new(),
+            depth: 0,
+            commit_pending: 0,
+            commit_published: 0,
+            #[cfg(feature = "stats")]
+            stats: Stats::EMPTY,
+        };
+        self.local += n;
+        // The child owns the forked range now, so `dec` must not reach back into it.
+        self.base = self.local;
+        Some(child)
+    }
+
+    /// Reserves `n * per_child` contiguous sequences and splits them, in order, into `n`
+    /// bounded children. Returns `None` if the block can't be reserved.
+    pub fn split_n(&mut self, n: usize, per_child: Sequence) -> Option<Vec<Self>> {
+        let total = per_child.checked_mul(n as Sequence)?;
+        if !self.reserve(total) {
+            return None;
+        }
+        Some(
+            (0..n)
+                .map(|_| self.fork_range(per_child).expect("reserved above"))
+                .collect(),
+        )
+    }
+}
+
+/// For a partition with exactly one writer: reserves one huge window up front and serves it
+/// without touching the global again until it runs out. The lag check is disabled, so
+/// output is gap-free as long as nothing else allocates from the same global.
+pub struct SingleWriterSequencer(Sequencer);
+
+impl SingleWriterSequencer {
+    pub const DEFAULT_WINDOW: Sequence = 1_000_000_000;
+
+    pub const fn new(window: Sequence) -> Self {
+        Self(Sequencer::new_no_lag(window))
+    }
+
+    pub fn inc(&mut self) -> Sequence {
+        self.0.inc()
+    }
+
+    pub fn try_inc(&mut self) -> Option<Sequence> {
+        self.0.try_inc()
+    }
+
+    pub fn remaining(&self) -> Sequence {
+        self.0.remaining()
+    }
+}
+
+impl Default for SingleWriterSequencer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+/// Common interface over sequencer flavors, for code that doesn't care which one it drives.
+pub trait SequenceAllocator {
+    fn inc(&mut self) -> Sequence;
+
+    /// The sequence `inc` returns next, unless it has to allocate a new window first.
+    fn peek(&self) -> Sequence;
+}
+
+impl<B: GlobalBackend> Drop for Sequencer<B> {
+    fn drop(&mut self) {
+        self.flush_commits();
+        self.reclaim();
+    }
+}
+
+impl<B: GlobalBackend> SequenceAllocator for Sequencer<B> {
+    fn inc(&mut self) -> Sequence {
+        Sequencer::inc(self)
+    }
+
+    fn peek(&self) -> Sequence {
+        self.local
+    }
+}
+
+impl SequenceAllocator for SingleWriterSequencer {
+    fn inc(&mut self) -> Sequence {
+        self.0.inc()
+    }
+
+    fn peek(&self) -> Sequence {
+        self.0.local
+    }
+}
+
+/// A batch would pass `Sequence::MAX`; `available` sequences could still be handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowError {
+    pub available: Sequence,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sequence space exhausted, {} sequences available",
+            self.available
+        )
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+/// The cached clock of `coarsetime` can't be relied on for timestamps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClockError {
+    /// The updater thread could not be spawned.
+    Updater(String),
+    /// The cached clock did not advance while the updater was running.
+    Frozen,
+}
+
+impl fmt::Display for ClockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Updater(e) => write!(f, "failed to start the coarsetime updater: {}", e),
+            Self::Frozen => write!(f, "the coarsetime clock does not advance"),
+        }
+    }
+}
+
+impl std::error::Error for ClockError {}
+
+const UPDATER_PERIOD_MS: u64 = 1;
+// The clock must move within this many updater periods.
+const FROZEN_AFTER_PERIODS: u32 = 20;
+
+// The one updater of the process, kept running until exit. The first call starts it and
+// waits for the clock to advance, which takes a few milliseconds.
+fn clock_updater() -> Result<(), ClockError> {
+    static UPDATER: OnceLock<Result<coarsetime::Updater, ClockError>> = OnceLock::new();
+    UPDATER
+        .get_or_init(|| {
+            let updater = coarsetime::Updater::new(UPDATER_PERIOD_MS)
+                .start()
+                .map_err(|e| ClockError::Updater(e.to_string()))?;
+            let before = coarsetime::Instant::recent();
+            let period = Duration::from_millis(UPDATER_PERIOD_MS);
+            for _ in 0..FROZEN_AFTER_PERIODS {
+                std::thread::sleep(period);
+                if coarsetime::Instant::recent() > before {
+                    return Ok(updater);
+                }
+            }
+            Err(ClockError::Frozen)
+        })
+        .as_ref()
+        .map(|_| ())
+        .map_err(Clone::clone)
+}
+
+// Moves the committed head over every buffered block that starts right above it or lower.
+// Blocks wholly below it were skipped over by an unordered `commit`.
+fn drain_reorder(pending: &mut BTreeMap<Sequence, Sequence>) {
+    let mut head = SEQUENCE_COMMITTED.load(Ordering::SeqCst);
+    while let Some((&start, &last)) = pending.first_key_value() {
+        if start > head.saturating_add(1) {
+            break;
+        }
+        pending.pop_first();
+        head = head.max(last);
+    }
+    SEQUENCE_COMMITTED.fetch_max(head, Ordering::SeqCst);
+    COMMIT_BUFFERED.store(pending.len(), Ordering::SeqCst);
+}
+
+// Out of line, and given only the ring, so that the rest of the sequencer can stay in
+// registers across `inc`s.
+#[inline(never)]
+fn next_staged(staged: &mut VecDeque<Range<Sequence>>) -> Option<Range<Sequence>> {
+    staged.pop_front()
+}
+
+#[cold]
+fn exhausted() -> ! {
+    panic!("sequencer exhausted")
+}
+
+#[cold]
+#[track_caller]
+const fn zero_step() -> ! {
+    panic!("step must be at least 1")
+}
+
+/// One sequencer behind a mutex, shared by reference between threads.
+pub struct SharedSequencer(Mutex<Sequencer>);
+
+impl SharedSequencer {
+    pub const fn new(step: Sequence, lag: Sequence) -> Self {
+        Self(Mutex::new(Sequencer::new(step, lag)))
+    }
+
+    pub fn inc(&self) -> Sequence {
+        self.0.lock().unwrap().inc()
+    }
+}
+
+/// A window shared between threads without a lock: sequences are claimed from it by CAS
+/// and a new one is fetched from the global once it runs out. There is no lag check.
+pub struct LockFreeSequencer {
+    next: AtomicSequence,
+    end: AtomicSequence,
+    step: Sequence,
+}
+
+impl LockFreeSequencer {
+    pub const fn new(step: Sequence) -> Self {
+        assert!(step > 0, "step must be at least 1");
+        Self {
+            next: AtomicSequence::new(0),
+            end: AtomicSequence::new(0),
+            step,
+        }
+    }
+
+    pub fn inc(&self) -> Sequence {
+        // Invariants: `next` only grows, as every window starts above all earlier ones, so
+        // a successful CAS on it can't suffer from ABA. A new window is installed into
+        // `next` before its `end` is published, so a reader that sees the new `end` can't
+        // claim a stale `next`. A stale, lower `end` only causes a spare refill.
+        loop {
+            let cur = self.next.load(Ordering::Acquire);
+            if cur < self.end.load(Ordering::Acquire) {
+                if self
+                    .next
+                    .compare_exchange_weak(cur, cur + 1, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    return cur;
+                }
+                continue;
+            }
+            let Some(start) = SEQUENCE_GLOBAL.allocate(self.step) else {
+                exhausted();
+            };
+            let end = start + self.step;
+            // Whoever installs a window takes its first sequence, so refills always make
+            // progress. Losing the race wastes the fetched window.
+            if self
+                .next
+                .compare_exchange(cur, start + 1, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.end.store(end, Ordering::Release);
+                return start;
+            }
+        }
+    }
+}
+
+/// Keeps released sequencers so their leftover windows are served before the global is hit.
+pub struct SequencerPool {
+    step: Sequence,
+    lag: Sequence,
+    idle: Mutex<Vec<Sequencer>>,
+}
+
+impl SequencerPool {
+    pub const fn new(step: Sequence, lag: Sequence) -> Self {
+        Self {
+            step,
+            lag,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn acquire(&self) -> Sequencer {
+        self.idle
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| Sequencer::new(self.step, self.lag))
+    }
+
+    /// Bounded sequencers are dropped rather than pooled, as they can never refill.
+    pub fn release(&self, sequencer: Sequencer) {
+        if !sequencer.bounded {
+            self.idle.lock().unwrap().push(sequencer);
+        }
     }
 }
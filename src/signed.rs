@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use crate::sequence::{AtomicSequence, Sequence, Sequencer};
+
+/// Sequences around an epoch, running from `i64::MIN` through zero up to `i64::MAX - 1`.
+///
+/// Backed by a plain `Sequencer` on a biased counter: flipping the top bit maps `u64` onto
+/// `i64` preserving order, so windows, the lag check and the overflow policy all carry
+/// over unchanged. `u64::MAX` is never handed out, so `i64::MAX - 1` is the last sequence.
+pub struct SignedSequencer(Sequencer);
+
+/// A global counter for `SignedSequencer`s, whose first sequence is `seed`.
+pub fn signed_global(seed: i64) -> Arc<AtomicSequence> {
+    Arc::new(AtomicSequence::new(to_unsigned(seed)))
+}
+
+impl SignedSequencer {
+    /// `global` must come from `signed_global`; sequencers sharing it never overlap.
+    pub fn new(global: Arc<AtomicSequence>, step: Sequence, lag: Sequence) -> Self {
+        let mut sequencer = Sequencer::new(step, lag);
+        sequencer.rebase(global);
+        Self(sequencer)
+    }
+
+    pub fn inc(&mut self) -> i64 {
+        to_signed(self.0.inc())
+    }
+
+    pub fn try_inc(&mut self) -> Option<i64> {
+        self.0.try_inc().map(to_signed)
+    }
+
+    /// Sequences left below `i64::MAX`, which the bias maps onto the unsigned ceiling.
+    pub fn remaining_global_space(&self) -> Sequence {
+        self.0.remaining_global_space()
+    }
+}
+
+const BIAS: Sequence = 1 << 63;
+
+fn to_signed(seq: Sequence) -> i64 {
+    (seq ^ BIAS) as i64
+}
+
+fn to_unsigned(seq: i64) -> Sequence {
+    seq as Sequence ^ BIAS
+}
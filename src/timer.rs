@@ -0,0 +1,142 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    Instant,
+    /// The x86_64 time stamp counter, with the `tsc` feature.
+    Tsc,
+}
+
+impl FromStr for Timer {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "instant" => Ok(Self::Instant),
+            "tsc" => Ok(Self::Tsc),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Instant => write!(f, "instant"),
+            Self::Tsc => write!(f, "tsc"),
+        }
+    }
+}
+
+/// Start of a timing loop, on whichever clock `init` selected.
+#[derive(Clone, Copy)]
+pub enum Start {
+    Instant(Instant),
+    #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+    Tsc(u64),
+}
+
+impl Start {
+    pub fn elapsed(self) -> Duration {
+        match self {
+            Self::Instant(start) => start.elapsed(),
+            #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+            Self::Tsc(start) => cycles_to_duration(rdtsc() - start, *CYCLES_PER_NS.get().unwrap()),
+        }
+    }
+}
+
+/// Counter ticks per nanosecond, measured by `init`; unset when timing with `Instant`.
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+static CYCLES_PER_NS: OnceLock<f64> = OnceLock::new();
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+pub fn start() -> Start {
+    match CYCLES_PER_NS.get() {
+        Some(_) => Start::Tsc(rdtsc()),
+        None => Start::Instant(Instant::now()),
+    }
+}
+
+#[cfg(not(all(feature = "tsc", target_arch = "x86_64")))]
+pub fn start() -> Start {
+    Start::Instant(Instant::now())
+}
+
+/// Selects the clock of all timing loops started afterwards and returns a line describing
+/// it. `Tsc` falls back to `Instant` where there is no counter to read.
+///
+/// The counter is calibrated against `Instant` over `CALIBRATION`. This assumes an
+/// invariant TSC, which ticks at a constant rate whatever the core frequency; on older
+/// CPUs without it, frequency scaling and sleep states skew every result. The counter is
+/// also not serializing, so a handful of cycles around each loop are attributed loosely.
+pub fn init(timer: Timer) -> String {
+    match timer {
+        Timer::Instant => "timer: instant".to_string(),
+        #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+        Timer::Tsc => {
+            let per_ns = *CYCLES_PER_NS.get_or_init(calibrate);
+            format!("timer: tsc at {:.3} cycles per ns", per_ns)
+        }
+        #[cfg(not(all(feature = "tsc", target_arch = "x86_64")))]
+        Timer::Tsc => "timer: tsc unavailable on this target, using instant".to_string(),
+    }
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+const CALIBRATION: Duration = Duration::from_millis(50);
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+fn calibrate() -> f64 {
+    let start = Instant::now();
+    let cycles = rdtsc();
+    while start.elapsed() < CALIBRATION {
+        std::hint::spin_loop();
+    }
+    let cycles = rdtsc() - cycles;
+    cycles as f64 / start.elapsed().as_nanos() as f64
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+fn rdtsc() -> u64 {
+    // SAFETY: `rdtsc` is available on every x86_64 CPU and has no side effects.
+    unsafe { std::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+fn cycles_to_duration(cycles: u64, per_ns: f64) -> Duration {
+    Duration::from_nanos((cycles as f64 / per_ns).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+    fn cycles_convert_at_the_calibrated_rate() {
+        assert_eq!(cycles_to_duration(3_000, 3.0), Duration::from_micros(1));
+        assert_eq!(
+            cycles_to_duration(2_500_000_000, 2.5),
+            Duration::from_secs(1)
+        );
+        // Rounded to the nearest nanosecond.
+        assert_eq!(cycles_to_duration(5, 3.0), Duration::from_nanos(2));
+        assert_eq!(cycles_to_duration(0, 3.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn timers_parse_back_from_their_names() {
+        for timer in [Timer::Instant, Timer::Tsc] {
+            assert_eq!(timer.to_string().parse(), Ok(timer));
+        }
+        assert_eq!("rdtsc".parse::<Timer>(), Err(()));
+    }
+}
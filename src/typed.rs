@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::sequence::{Sequence, Sequencer};
+
+/// A sequence branded with the `Tag` of the sequencer that issued it, so that indices of
+/// different spaces can't be mixed up. Zero-cost over a plain `Sequence`.
+#[repr(transparent)]
+pub struct TypedSequence<Tag>(Sequence, PhantomData<fn() -> Tag>);
+
+impl<Tag> TypedSequence<Tag> {
+    pub const fn new(seq: Sequence) -> Self {
+        Self(seq, PhantomData)
+    }
+
+    pub const fn get(self) -> Sequence {
+        self.0
+    }
+}
+
+// Implemented by hand: derives would require `Tag` itself to implement the traits.
+
+impl<Tag> Clone for TypedSequence<Tag> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Tag> Copy for TypedSequence<Tag> {}
+
+impl<Tag> PartialEq for TypedSequence<Tag> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Tag> Eq for TypedSequence<Tag> {}
+
+impl<Tag> PartialOrd for TypedSequence<Tag> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Tag> Ord for TypedSequence<Tag> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<Tag> Hash for TypedSequence<Tag> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<Tag> fmt::Debug for TypedSequence<Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedSequence").field(&self.0).finish()
+    }
+}
+
+/// A `Sequencer` whose sequences are branded with `Tag`.
+///
+/// ```
+/// use rust_benchmark_atomic::typed::{TypedSequence, TypedSequencer};
+///
+/// struct Orders;
+///
+/// let mut orders = TypedSequencer::<Orders>::new(128, 2048);
+/// let order: TypedSequence<Orders> = orders.inc();
+/// assert!(orders.inc() > order);
+/// assert_eq!(std::mem::size_of_val(&order), std::mem::size_of::<u64>());
+/// ```
+///
+/// Sequences of two tags don't mix:
+///
+/// ```compile_fail,E0308
+/// use rust_benchmark_atomic::typed::{TypedSequence, TypedSequencer};
+///
+/// struct Orders;
+/// struct Users;
+///
+/// let mut users = TypedSequencer::<Users>::new(128, 2048);
+/// let order: TypedSequence<Orders> = users.inc();
+/// ```
+pub struct TypedSequencer<Tag> {
+    inner: Sequencer,
+    _tag: PhantomData<fn() -> Tag>,
+}
+
+impl<Tag> TypedSequencer<Tag> {
+    pub const fn new(step: Sequence, lag: Sequence) -> Self {
+        Self {
+            inner: Sequencer::new(step, lag),
+            _tag: PhantomData,
+        }
+    }
+
+    pub fn inc(&mut self) -> TypedSequence<Tag> {
+        TypedSequence::new(self.inner.inc())
+    }
+
+    pub fn try_inc(&mut self) -> Option<TypedSequence<Tag>> {
+        self.inner.try_inc().map(TypedSequence::new)
+    }
+
+    pub fn inner(&self) -> &Sequencer {
+        &self.inner
+    }
+}
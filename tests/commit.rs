@@ -0,0 +1,142 @@
+//! Tests on the process-wide committed head, one at a time.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use rust_benchmark_atomic::sequence::{
+    try_wait_committed, wait_until_committed, wait_until_committed_timeout, Sequence, Sequencer,
+    SEQUENCE_COMMITTED,
+};
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Holds the committed head for one test, starting from 0. Every test fills the gaps it
+/// leaves in the shared reorder buffer before it ends.
+fn committed() -> MutexGuard<'static, ()> {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    SEQUENCE_COMMITTED.store(0, Ordering::Relaxed);
+    guard
+}
+
+#[test]
+fn ordered_commits_wait_for_the_gap() {
+    let _head = committed();
+    let s = Sequencer::new(16, 64);
+    s.commit_ordered(1);
+    assert_eq!(s.committed_head(), 1);
+    s.commit_ordered(3);
+    assert_eq!(s.committed_head(), 1);
+    s.commit_ordered(2);
+    assert_eq!(s.committed_head(), 3);
+    // Already covered.
+    s.commit_ordered(2);
+    assert_eq!(s.committed_head(), 3);
+}
+
+#[test]
+fn ordered_commits_from_many_threads() {
+    const THREADS: Sequence = 4;
+    const PER_THREAD: Sequence = 2000;
+    let _head = committed();
+    // Thread `t` commits every `THREADS`th sequence from `t + 1`, so each one keeps running
+    // ahead of the others and buffering.
+    let handles = (0..THREADS)
+        .map(|t| {
+            std::thread::spawn(move || {
+                let s = Sequencer::new(16, 64);
+                for i in 0..PER_THREAD {
+                    s.commit_ordered(t + 1 + i * THREADS);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(
+        SEQUENCE_COMMITTED.load(Ordering::Relaxed),
+        THREADS * PER_THREAD
+    );
+}
+
+#[test]
+fn unordered_commits_only_move_the_head_up() {
+    let _head = committed();
+    let s = Sequencer::new(16, 64);
+    let mut last = s.committed_head();
+    for seq in [5, 2, 9, 9, 7, 1, 12] {
+        s.commit(seq);
+        let head = s.committed_head();
+        assert!(head >= last, "the head went back from {} to {}", last, head);
+        assert_eq!(head, last.max(seq));
+        last = head;
+    }
+    assert!(try_wait_committed(12));
+    assert!(!try_wait_committed(13));
+}
+
+#[test]
+fn readers_wait_for_the_commit() {
+    let _head = committed();
+    assert!(!try_wait_committed(10));
+    assert!(!wait_until_committed_timeout(10, Duration::from_millis(20)));
+
+    let published = Arc::new(AtomicBool::new(false));
+    let producer = {
+        let published = published.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            published.store(true, Ordering::Relaxed);
+            Sequencer::new(16, 64).commit(10);
+        })
+    };
+    wait_until_committed(10);
+    // The commit's release pairs with the wait's acquire.
+    assert!(published.load(Ordering::Relaxed));
+    assert!(try_wait_committed(10));
+    assert!(wait_until_committed_timeout(5, Duration::ZERO));
+    producer.join().unwrap();
+}
+
+#[test]
+fn batched_commits_publish_once_per_step() {
+    let _head = committed();
+    let mut s = Sequencer::new(16, 64);
+    for seq in 1..=1000 {
+        s.commit_batched(seq);
+        let head = s.committed_head();
+        // Late, never early, and never more than a step behind.
+        assert!(head <= seq && seq - head < 16, "head {} at {}", head, seq);
+    }
+    #[cfg(feature = "stats")]
+    assert_eq!(s.stats().commit_publishes, 1000 / 16);
+    s.flush_commits();
+    assert_eq!(s.committed_head(), 1000);
+    #[cfg(feature = "stats")]
+    assert_eq!(s.stats().commit_publishes, 1000 / 16 + 1);
+
+    s.commit_batched(1001);
+    drop(s);
+    assert!(try_wait_committed(1001));
+}
+
+#[test]
+fn adjacent_ranges_join_the_head() {
+    let _head = committed();
+    let s = Sequencer::new(16, 64);
+    s.commit_range(1..11);
+    assert_eq!(s.committed_head(), 10);
+    s.commit_range(11..21);
+    assert_eq!(s.committed_head(), 20);
+
+    // Ahead of the head, the far block waits for the near one.
+    s.commit_range(31..41);
+    assert_eq!(s.committed_head(), 20);
+    s.commit_range(21..31);
+    assert_eq!(s.committed_head(), 40);
+}
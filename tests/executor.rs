@@ -0,0 +1,66 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use rust_benchmark_atomic::{
+    executor::{run_together, Executor, Pool, Spawn},
+    sequence::{Sequence, SEQUENCER},
+};
+
+// Every body sees how many threads had finished their setup when it started.
+fn setups_seen(executor: &dyn Executor, threads: usize) -> Vec<usize> {
+    let ready = Arc::new(AtomicUsize::new(0));
+    run_together(executor, threads, move |i| {
+        // Staggered, so that without the barrier early bodies would start before late setups.
+        std::thread::sleep(std::time::Duration::from_millis(2 * i as u64));
+        ready.fetch_add(1, Ordering::SeqCst);
+        let ready = ready.clone();
+        move || ready.load(Ordering::SeqCst)
+    })
+}
+
+#[test]
+fn bodies_start_once_every_thread_is_set_up() {
+    assert_eq!(setups_seen(&Spawn, 8), [8; 8]);
+    let pool = Pool::default();
+    for _ in 0..2 {
+        assert_eq!(setups_seen(&pool, 8), [8; 8]);
+    }
+}
+
+// Thread `i` draws `loops` sequences from its thread-local sequencer.
+fn draws(executor: &dyn Executor, threads: usize, loops: usize) -> Vec<(usize, Vec<Sequence>)> {
+    run_together(executor, threads, move |i| {
+        move || {
+            let seqs = (0..loops).map(|_| SEQUENCER.with(|s| s.borrow_mut().inc()));
+            (i, seqs.collect())
+        }
+    })
+}
+
+#[test]
+fn pooled_sequencers_keep_their_windows_across_runs() {
+    const THREADS: usize = 4;
+    const LOOPS: usize = 100;
+    let pool = Pool::default();
+    let mut first = draws(&pool, THREADS, LOOPS);
+    let mut second = draws(&pool, THREADS, LOOPS);
+    first.sort_unstable();
+    second.sort_unstable();
+
+    // The same worker runs thread `i` both times, and carries on in its window; the few
+    // windows drawn are far from tripping the lag.
+    for ((i, first), (_, second)) in first.iter().zip(&second) {
+        assert_eq!(second[0], first[LOOPS - 1] + 1, "thread {} started over", i);
+    }
+    let mut all = first
+        .into_iter()
+        .chain(second)
+        .flat_map(|(_, seqs)| seqs)
+        .collect::<Vec<_>>();
+    assert_eq!(all.len(), 2 * THREADS * LOOPS);
+    all.sort_unstable();
+    all.dedup();
+    assert_eq!(all.len(), 2 * THREADS * LOOPS, "duplicate sequences");
+}
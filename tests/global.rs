@@ -0,0 +1,301 @@
+//! Tests on the process-wide `SEQUENCE_GLOBAL`, one at a time.
+
+use std::sync::{atomic::Ordering, Arc, Mutex, MutexGuard};
+
+use rust_benchmark_atomic::sequence::{
+    advance_global_to, persist_global, restore_global, AtomicSequence, LockFreeSequencer, Sequence,
+    Sequencer, SequencerPool, SingleWriterSequencer, SEQUENCE_GLOBAL,
+};
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Holds the global for one test, starting at `head`, and resets it when dropped.
+struct GlobalAt(#[allow(dead_code)] MutexGuard<'static, ()>);
+
+fn global_at(head: Sequence) -> GlobalAt {
+    // A test that panicked while holding the lock has still reset the global.
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    SEQUENCE_GLOBAL.store(head, Ordering::Relaxed);
+    GlobalAt(guard)
+}
+
+impl Drop for GlobalAt {
+    fn drop(&mut self) {
+        SEQUENCE_GLOBAL.store(0, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn the_default_global_never_wraps() {
+    let _global = global_at(Sequence::MAX - 100);
+    let mut s = Sequencer::new(128, 128);
+    assert_eq!(s.try_inc(), None);
+    assert_eq!(Sequencer::new(128, 128).try_inc(), None);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), Sequence::MAX - 100);
+}
+
+#[test]
+#[should_panic(expected = "sequencer exhausted")]
+fn lock_free_refuses_to_wrap() {
+    let _global = global_at(Sequence::MAX - 100);
+    LockFreeSequencer::new(128).inc();
+}
+
+#[test]
+fn made_up_parts_never_rewind_the_global() {
+    let _global = global_at(1000);
+    let mut s = Sequencer::from_parts(10, 1000, 128, 2048);
+    assert_eq!(s.inc(), 10);
+    assert!(!s.reclaim());
+    drop(s);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 1000);
+
+    // Its own windows are handed back as usual.
+    let mut s = Sequencer::from_parts(10, 10, 128, 2048);
+    assert_eq!(s.inc(), 1000);
+    drop(s);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 1001);
+}
+
+#[test]
+fn parts_round_trip() {
+    let _global = global_at(0);
+    let mut original = Sequencer::new(16, 64);
+    let drawn = (0..5).map(|_| original.inc()).collect::<Vec<_>>();
+    let parts = original.into_parts();
+    assert_eq!(parts, (5, 16, 16, 64));
+    let mut restored = Sequencer::from_parts(parts.0, parts.1, parts.2, parts.3);
+    restored.check_invariants().unwrap();
+    let rest = (0..40).map(|_| restored.inc()).collect::<Vec<_>>();
+
+    // The same draws from one sequencer that was never taken apart.
+    SEQUENCE_GLOBAL.store(0, Ordering::Relaxed);
+    let mut twin = Sequencer::new(16, 64);
+    let all = (0..45).map(|_| twin.inc()).collect::<Vec<_>>();
+    assert_eq!([drawn, rest].concat(), all);
+}
+
+#[test]
+#[should_panic(expected = "local 5 beyond target 4")]
+fn parts_out_of_order_are_refused() {
+    Sequencer::from_parts(5, 4, 16, 64);
+}
+
+#[test]
+fn pooled_sequencers_keep_their_window() {
+    let _global = global_at(0);
+    let pool = SequencerPool::new(16, 64);
+    let mut s = pool.acquire();
+    assert_eq!((0..5).map(|_| s.inc()).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    pool.release(s);
+
+    let mut s = pool.acquire();
+    assert_eq!(s.local_window(), 5..16);
+    assert_eq!(s.inc(), 5);
+    assert_eq!(
+        SEQUENCE_GLOBAL.load(Ordering::Relaxed),
+        16,
+        "the window was not reused"
+    );
+    // A second sequencer while the first is out starts a window of its own.
+    assert_eq!(pool.acquire().inc(), 16);
+}
+
+#[test]
+fn parts_near_the_end_run_out_cleanly() {
+    let _global = global_at(Sequence::MAX - 64);
+    let mut s = Sequencer::from_parts(Sequence::MAX - 66, Sequence::MAX - 64, 128, 2048);
+    assert_eq!(s.try_inc(), Some(Sequence::MAX - 66));
+    assert_eq!(s.try_inc(), Some(Sequence::MAX - 65));
+    // A whole step no longer fits below the end.
+    assert_eq!(s.try_inc(), None);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), Sequence::MAX - 64);
+}
+
+#[test]
+fn snapshots_restore_past_the_margin() {
+    let _global = global_at(12_345);
+    let mut snapshot = vec![];
+    persist_global(&mut snapshot).unwrap();
+
+    SEQUENCE_GLOBAL.store(0, Ordering::Relaxed);
+    assert_eq!(restore_global(snapshot.as_slice(), 0).unwrap(), 12_345);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 12_345);
+
+    // Whatever was handed out after the snapshot lies below the restored head.
+    Sequencer::new(128, 2048).inc();
+    assert_eq!(restore_global(snapshot.as_slice(), 1024).unwrap(), 13_369);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 13_369);
+
+    assert!(restore_global(&snapshot[..4], 0).is_err());
+    assert!(restore_global(Sequence::MAX.to_le_bytes().as_slice(), 1).is_err());
+}
+
+#[test]
+fn advancing_never_moves_the_global_back() {
+    let _global = global_at(1000);
+    advance_global_to(10);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 1000);
+    advance_global_to(1000);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 1000);
+    advance_global_to(5000);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 5000);
+    // Sequencers allocating from now on start at the advanced head.
+    assert_eq!(Sequencer::new(16, 64).inc(), 5000);
+}
+
+#[test]
+fn lock_free_sequencers_never_collide() {
+    const THREADS: usize = 8;
+    const LOOPS: usize = 20_000;
+    let _global = global_at(0);
+    // A small step, so that threads keep racing to replace the shared window.
+    let shared = Arc::new(LockFreeSequencer::new(16));
+    let handles = (0..THREADS)
+        .map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || (0..LOOPS).map(|_| shared.inc()).collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+    let mut all = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    all.sort_unstable();
+    all.dedup();
+    assert_eq!(all.len(), THREADS * LOOPS, "duplicate sequences");
+    assert!(*all.last().unwrap() < SEQUENCE_GLOBAL.load(Ordering::Relaxed));
+}
+
+#[test]
+fn timestamped_sequencers_see_the_clock_move() {
+    let _global = global_at(0);
+    let mut s = Sequencer::timestamped(16, 64).unwrap();
+    let (_, first) = s.inc_with_timestamp();
+    // Without the updater the cached clock would stay where it is.
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    let (_, later) = s.inc_with_timestamp();
+    assert!(later > first, "the clock stayed at {}", first);
+}
+
+#[test]
+fn remaining_space_is_what_the_global_has_left() {
+    let _global = global_at(Sequence::MAX - 10_000);
+    let mut s = Sequencer::new(128, 1024);
+    assert_eq!(s.remaining_global_space(), 10_000);
+    s.inc();
+    assert_eq!(s.remaining_global_space(), 10_000 - 128);
+    advance_global_to(Sequence::MAX - 1);
+    assert_eq!(s.remaining_global_space(), 1);
+
+    // A bounded child only counts its own range.
+    let child = s.fork_range(16).unwrap();
+    assert_eq!(child.remaining_global_space(), 16);
+}
+
+#[test]
+fn staged_windows_are_served_in_order() {
+    let _global = global_at(0);
+    let mut s = Sequencer::new_buffered(16, 1 << 20, 2);
+    assert_eq!(s.inc(), 0);
+    // Another sequencer takes the window right after, so the staged ones sit above it.
+    let mut other = Sequencer::new(16, 1 << 20);
+    assert_eq!(other.inc(), 16);
+    assert!(s.prefetch_next_window());
+    assert!(!s.prefetch_next_window());
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 64);
+    s.check_invariants().unwrap();
+
+    let seqs = (0..47).map(|_| s.inc()).collect::<Vec<_>>();
+    let expected = (1..16).chain(32..64).collect::<Vec<_>>();
+    assert_eq!(seqs, expected);
+    // Consumed without touching the global.
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 64);
+    s.check_invariants().unwrap();
+    assert_eq!(s.inc(), 64);
+}
+
+#[test]
+fn a_window_of_step_0_is_never_allocated() {
+    let _global = global_at(100);
+    // `from_parts` keeps a step of 0 for `check_invariants` to report. Lagging or not, it
+    // serves the window it has and stops there.
+    for lag in [64, Sequencer::NO_LAG] {
+        let mut s = Sequencer::from_parts(10, 12, 0, lag);
+        let seqs = [s.try_inc(), s.try_inc(), s.try_inc()];
+        assert_eq!(seqs, [Some(10), Some(11), None]);
+        assert_eq!(s.remaining(), 0);
+    }
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 100);
+}
+
+#[test]
+fn a_single_writer_allocates_once_per_window() {
+    const WINDOW: Sequence = 1 << 20;
+    let _global = global_at(0);
+    let mut s = SingleWriterSequencer::new(WINDOW);
+    assert_eq!(s.inc(), 0);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), WINDOW);
+    // Another writer moving the global doesn't make this one allocate either.
+    advance_global_to(WINDOW * 3);
+    for expected in 1..WINDOW {
+        assert_eq!(s.inc(), expected);
+    }
+    assert_eq!(s.remaining(), 0);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), WINDOW * 3);
+    assert_eq!(s.inc(), WINDOW * 3);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), WINDOW * 4);
+}
+
+#[test]
+fn rebased_sequencers_only_draw_from_the_new_counter() {
+    let _global = global_at(0);
+    let mut s = Sequencer::new(16, 256);
+    let old = (0..5).map(|_| s.inc()).collect::<Vec<_>>();
+    assert_eq!(old, [0, 1, 2, 3, 4]);
+    // Another sequencer's window sits above, so the rest of this one can't go back.
+    let mut other = Sequencer::new(16, 256);
+    assert_eq!(other.inc(), 16);
+
+    let new = Arc::new(AtomicSequence::new(1000));
+    s.rebase(new.clone());
+    let seqs = (0..40).map(|_| s.inc()).collect::<Vec<_>>();
+    assert_eq!(seqs, (1000..1040).collect::<Vec<_>>());
+    assert_eq!(new.load(Ordering::Relaxed), 1048);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 32);
+
+    // Rebased again, the window on the new counter is handed back there.
+    s.rebase(Arc::new(AtomicSequence::new(5000)));
+    assert_eq!(new.load(Ordering::Relaxed), 1040);
+    assert_eq!(s.inc(), 5000);
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), 32);
+}
+
+#[test]
+fn staged_windows_outlive_a_failed_allocation() {
+    const MAX: Sequence = Sequence::MAX;
+    let _global = global_at(MAX - 64);
+    let mut s = Sequencer::new_buffered(16, 1 << 20, 2);
+    assert_eq!(s.inc(), MAX - 64);
+    assert!(s.prefetch_next_window());
+    // Too large for what is left, so the staged windows stay.
+    assert!(!s.reserve(32));
+    s.check_invariants().unwrap();
+    let seqs = std::iter::from_fn(|| s.try_inc()).collect::<Vec<_>>();
+    assert_eq!(seqs, (MAX - 63..MAX).collect::<Vec<_>>());
+}
+
+#[test]
+fn staged_windows_outlive_a_lag_trip_at_the_end() {
+    const MAX: Sequence = Sequence::MAX;
+    let _global = global_at(MAX - 64);
+    let mut s = Sequencer::new_buffered(16, 32, 2);
+    assert_eq!(s.inc(), MAX - 64);
+    assert!(s.prefetch_next_window());
+    let mut other = Sequencer::new(16, 32);
+    assert_eq!(other.inc(), MAX - 16);
+    // Lagging, but there is no fresh window left to trip to.
+    let seqs = std::iter::from_fn(|| s.try_inc()).collect::<Vec<_>>();
+    assert_eq!(seqs, (MAX - 63..MAX - 16).collect::<Vec<_>>());
+    assert_eq!(SEQUENCE_GLOBAL.load(Ordering::Relaxed), MAX);
+}
@@ -0,0 +1,39 @@
+#![cfg(all(feature = "mmap", unix))]
+
+use std::sync::{atomic::Ordering, Arc};
+
+use rust_benchmark_atomic::{mmap::MmapGlobal, sequence::Sequencer};
+
+#[test]
+fn two_mappings_share_one_sequence_space() {
+    const LOOPS: usize = 10_000;
+    let path = std::env::temp_dir().join(format!("sequence-mmap-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // Each mapping stands in for a process of its own.
+    let handles = (0..2)
+        .map(|_| {
+            let global = Arc::new(MmapGlobal::open(&path).unwrap());
+            std::thread::spawn(move || {
+                let mut s = Sequencer::with_backend(global, 16, 64);
+                (0..LOOPS).map(|_| s.inc()).collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut all = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    all.sort_unstable();
+    all.dedup();
+    assert_eq!(
+        all.len(),
+        2 * LOOPS,
+        "the mappings handed out the same sequences"
+    );
+
+    // The counter lives in the file, past the mappings.
+    let head = MmapGlobal::open(&path).unwrap().load(Ordering::Relaxed);
+    assert!(head > *all.last().unwrap());
+    std::fs::remove_file(&path).unwrap();
+}
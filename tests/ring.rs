@@ -0,0 +1,29 @@
+use rust_benchmark_atomic::{
+    ring::RingIndexer,
+    sequence::{Sequence, Sequencer},
+};
+
+// Two laps of claims from a window starting at `start`, made up so that the global is never
+// touched.
+fn two_laps(start: Sequence, capacity: usize) -> Vec<usize> {
+    let end = start + 2 * capacity as Sequence;
+    let sequencer = Sequencer::from_parts(start, end, capacity as Sequence, Sequencer::NO_LAG);
+    let mut ring = RingIndexer::new(sequencer, capacity);
+    (0..2 * capacity).map(|_| ring.claim()).collect()
+}
+
+#[test]
+fn claims_wrap_around_the_ring() {
+    for capacity in [1, 6, 8, 12] {
+        for start in [0, 5, Sequence::MAX - 2 * capacity as Sequence] {
+            let slots = two_laps(start, capacity);
+            let first = (start % capacity as Sequence) as usize;
+            let expected = (0..2 * capacity)
+                .map(|i| (first + i) % capacity)
+                .collect::<Vec<_>>();
+            assert_eq!(slots, expected, "capacity {}, from {}", capacity, start);
+            let (lap, next) = slots.split_at(capacity);
+            assert_eq!(lap, next);
+        }
+    }
+}
@@ -0,0 +1,656 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use rust_benchmark_atomic::sequence::{
+    compact, AtomicSequence, GlobalBackend, OverflowError, Sequence, SequenceAllocator, Sequencer,
+};
+
+/// A sequencer on a global of its own, so tests running in parallel don't move it.
+fn private(step: Sequence, lag: Sequence) -> (Sequencer<Arc<AtomicSequence>>, Arc<AtomicSequence>) {
+    let global = Arc::new(AtomicSequence::new(0));
+    (Sequencer::with_backend(global.clone(), step, lag), global)
+}
+
+#[test]
+fn fork_range_splits_the_window() {
+    let (mut parent, global) = private(64, 64);
+    parent.reserve(64);
+    let first = parent.inc();
+    let mut child = parent.fork_range(16).unwrap();
+    let head = global.head();
+
+    let from_child = (0..16).map(|_| child.inc()).collect::<Vec<_>>();
+    assert_eq!(from_child, (first + 1..first + 17).collect::<Vec<_>>());
+    assert_eq!(child.try_inc(), None);
+    assert!(child.is_exhausted());
+    assert_eq!(parent.inc(), first + 17);
+    assert_eq!(global.head(), head, "the child allocated from the global");
+
+    assert!(parent.fork_range(parent.remaining() + 1).is_none());
+}
+
+#[test]
+fn bounded_children_ignore_the_lag() {
+    let (mut parent, global) = private(64, 64);
+    let mut child = parent.fork_range(0).unwrap();
+    assert_eq!(child.try_inc(), None);
+
+    parent.reserve(32);
+    let mut child = parent.fork_range(32).unwrap();
+    // Far past the lag of the range the child serves from.
+    global.allocate(1 << 20);
+    let start = child.inc();
+    for i in 1..32 {
+        assert_eq!(child.inc(), start + i);
+    }
+    #[cfg(feature = "stats")]
+    assert_eq!(child.stats().lag_trips, 0);
+}
+
+#[test]
+fn windows_past_the_end_are_refused() {
+    let (mut s, global) = private(128, 128);
+    global.store(Sequence::MAX - 100, Ordering::Relaxed);
+    assert_eq!(s.try_inc(), None);
+    assert_eq!(s.try_inc(), None);
+    assert_eq!(global.head(), Sequence::MAX - 100, "the global wrapped");
+    let mut fresh = Sequencer::with_backend(global.clone(), 128, 128);
+    assert_eq!(fresh.try_inc(), None);
+    // Smaller windows still fit.
+    let mut small = Sequencer::with_backend(global.clone(), 100, 100);
+    assert_eq!(small.try_inc(), Some(Sequence::MAX - 100));
+}
+
+#[test]
+fn the_last_window_is_served_to_the_end() {
+    let (mut s, global) = private(128, Sequencer::NO_LAG);
+    global.store(Sequence::MAX - 256, Ordering::Relaxed);
+    let seqs = std::iter::from_fn(|| s.try_inc()).collect::<Vec<_>>();
+    assert_eq!(
+        seqs,
+        (Sequence::MAX - 256..Sequence::MAX).collect::<Vec<_>>()
+    );
+    assert_eq!(global.head(), Sequence::MAX);
+    assert_eq!(s.remaining_global_space(), 0);
+    assert!(s.inc_n_checked(1).is_err());
+}
+
+#[test]
+fn lags_near_the_end_never_trip() {
+    let (mut s, global) = private(16, Sequence::MAX - 1);
+    global.store(Sequence::MAX - 64, Ordering::Relaxed);
+    for _ in 0..64 {
+        assert!(s.try_inc().is_some());
+    }
+    assert_eq!(s.try_inc(), None);
+}
+
+#[test]
+fn next_multiple_of_rounds_up() {
+    let (mut s, _) = private(16, 64);
+    assert_eq!(s.next_multiple_of(10), 0);
+    assert_eq!(s.next_multiple_of(10), 10);
+    s.inc();
+    s.inc();
+    assert_eq!(s.next_multiple_of(10), 20);
+    assert_eq!(s.inc(), 21);
+    assert_eq!(s.next_multiple_of(10), 30);
+}
+
+#[test]
+fn inc_unchecked_serves_the_reserved_window() {
+    let (mut s, _) = private(16, 64);
+    assert!(s.reserve(40));
+    let first = s.inc();
+    // SAFETY: 39 of the 40 reserved sequences are left.
+    let rest = (0..39)
+        .map(|_| unsafe { s.inc_unchecked() })
+        .collect::<Vec<_>>();
+    assert_eq!(rest, (first + 1..first + 40).collect::<Vec<_>>());
+    assert_eq!(s.remaining(), 0);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "inc_unchecked without budget")]
+fn inc_unchecked_without_budget_is_caught() {
+    let (mut s, _) = private(16, 64);
+    // SAFETY: none; this is the misuse the debug assertion is there for.
+    unsafe { s.inc_unchecked() };
+}
+
+#[test]
+fn the_local_window_shrinks_from_the_front() {
+    let (mut s, global) = private(16, 64);
+    assert!(s.local_window().is_empty());
+    s.inc();
+    assert_eq!(s.local_window(), 1..16);
+    s.inc();
+    assert_eq!(s.local_window(), 2..16);
+    (2..16).for_each(|_| {
+        s.inc();
+    });
+    assert!(s.local_window().is_empty());
+    // Someone else allocates in between, so the next window doesn't follow on.
+    global.allocate(100);
+    s.inc();
+    assert_eq!(s.local_window(), 117..132);
+}
+
+#[test]
+fn bounded_sequencers_are_exhausted_after_the_last_sequence() {
+    let (mut parent, _) = private(64, 64);
+    parent.reserve(64);
+    let mut child = parent.fork_range(3).unwrap();
+    for _ in 0..3 {
+        assert!(!child.is_exhausted());
+        child.inc();
+    }
+    assert!(child.is_exhausted());
+    assert_eq!(child.try_inc(), None);
+
+    // Unbounded ones refill instead.
+    let (mut s, _) = private(1, 1);
+    s.inc();
+    assert!(!s.is_exhausted());
+}
+
+fn draw(allocator: &mut dyn SequenceAllocator, n: usize) -> Vec<Sequence> {
+    (0..n).map(|_| allocator.inc()).collect()
+}
+
+#[test]
+fn sequencers_drive_through_the_trait() {
+    let (mut s, _) = private(4, 16);
+    assert_eq!(draw(&mut s, 6), [0, 1, 2, 3, 4, 5]);
+    assert_eq!(s.peek(), 6);
+    assert_eq!(draw(&mut s, 1), [6]);
+}
+
+#[test]
+fn dec_takes_back_the_last_inc() {
+    let (mut s, _) = private(16, 64);
+    assert_eq!(s.dec(), None);
+    let seq = s.inc();
+    s.inc();
+    let local = s.local();
+    let last = s.inc();
+    assert_eq!(s.dec(), Some(last));
+    assert_eq!(s.local(), local);
+    assert_eq!(s.inc(), last);
+    assert_eq!(s.dec(), Some(last));
+    assert_eq!(s.dec(), Some(seq + 1));
+    assert_eq!(s.dec(), Some(seq));
+    // Not past the start of the window.
+    assert_eq!(s.dec(), None);
+    #[cfg(feature = "stats")]
+    {
+        // Everything was handed back, so none of the window counts as used.
+        assert_eq!(s.stats().incs, 0);
+        assert_eq!(s.stats().average_utilization(), 0.0);
+        s.inc();
+        assert_eq!(s.stats().incs, 1);
+    }
+}
+
+#[test]
+fn try_inc_local_never_allocates() {
+    let (mut s, global) = private(8, 64);
+    assert_eq!(s.try_inc_local(), None);
+    assert_eq!(global.head(), 0);
+    s.reserve(8);
+    let seqs = std::iter::from_fn(|| s.try_inc_local()).collect::<Vec<_>>();
+    assert_eq!(seqs, (0..8).collect::<Vec<_>>());
+    assert_eq!(global.head(), 8);
+
+    // Nor serves a window the lag check would abandon.
+    s.reserve(8);
+    global.allocate(100);
+    assert_eq!(s.try_inc_local(), None);
+    assert_eq!(s.remaining(), 8);
+}
+
+#[test]
+#[cfg(feature = "stats")]
+fn frequent_allocs_hint_at_a_larger_step() {
+    // A step of 4 allocates every 4 incs.
+    let (mut s, _) = private(4, 64);
+    (0..1000).for_each(|_| {
+        s.inc();
+    });
+    let hint = s.tuning_hint();
+    assert!(hint.step > 4, "{:?}", hint);
+    assert!(hint.rationale.contains("allocs dominate"), "{:?}", hint);
+
+    let (mut s, _) = private(128, 2048);
+    (0..1000).for_each(|_| {
+        s.inc();
+    });
+    assert_eq!(s.tuning_hint().step, 128);
+}
+
+#[test]
+#[cfg(feature = "metrics")]
+fn metrics_follow_the_exposition_format() {
+    let (mut s, _) = private(16, 64);
+    (0..40).for_each(|_| {
+        s.inc();
+    });
+    let text = s.metrics_text();
+    for (name, kind) in [
+        ("sequencer_allocs_total", "counter"),
+        ("sequencer_incs_total", "counter"),
+        ("sequencer_lag_trips_total", "counter"),
+        ("sequencer_global_head", "gauge"),
+    ] {
+        assert!(text.contains(&format!("# HELP {} ", name)), "{}", text);
+        assert!(
+            text.contains(&format!("# TYPE {} {}\n", name, kind)),
+            "{}",
+            text
+        );
+    }
+    let sample = |line: &str| {
+        let (name, value) = line.rsplit_once(' ').unwrap();
+        (name.to_string(), value.parse::<u64>().unwrap())
+    };
+    let samples = text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(sample)
+        .collect::<Vec<_>>();
+    assert!(samples.contains(&("sequencer_allocs_total".to_string(), 3)));
+    assert!(samples.contains(&("sequencer_incs_total{path=\"fast\"}".to_string(), 37)));
+    assert!(samples.contains(&("sequencer_global_head".to_string(), 48)));
+}
+
+#[test]
+fn split_children_tile_the_block() {
+    let (mut parent, _) = private(16, 64);
+    parent.inc();
+    let mut children = parent.split_n(4, 10).unwrap();
+    let seqs = children
+        .iter_mut()
+        .map(|child| std::iter::from_fn(|| child.try_inc()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert!(seqs.iter().all(|seqs| seqs.len() == 10));
+    let first = seqs[0][0];
+    for (i, seqs) in seqs.iter().enumerate() {
+        let start = first + 10 * i as Sequence;
+        assert_eq!(*seqs, (start..start + 10).collect::<Vec<_>>());
+    }
+    // The parent carries on past the block.
+    assert_eq!(parent.inc(), first + 40);
+
+    assert!(parent.split_n(2, Sequence::MAX / 2 + 1).is_none());
+}
+
+#[test]
+fn utilization_fills_with_the_window() {
+    let (mut s, _) = private(8, 64);
+    assert_eq!(s.window_utilization(), 0.0);
+    s.reserve(8);
+    assert_eq!(s.window_utilization(), 0.0);
+    let mut last = 0.0;
+    for _ in 0..8 {
+        s.inc();
+        assert!(s.window_utilization() > last);
+        last = s.window_utilization();
+    }
+    assert_eq!(last, 1.0);
+    // The next window starts out empty again.
+    s.inc();
+    assert_eq!(s.window_utilization(), 1.0 / 8.0);
+    #[cfg(feature = "stats")]
+    assert_eq!(s.stats().average_utilization(), 9.0 / 16.0);
+}
+
+#[test]
+fn saturating_reserve_clamps_to_the_bound() {
+    let (mut parent, global) = private(64, 64);
+    parent.reserve(64);
+    let mut child = parent.fork_range(10).unwrap();
+    (0..7).for_each(|_| {
+        child.inc();
+    });
+    let head = global.head();
+    assert_eq!(child.saturating_reserve(2), 2);
+    assert_eq!(child.saturating_reserve(100), 3);
+    assert_eq!(global.head(), head, "a bounded child allocated");
+    let rest = std::iter::from_fn(|| child.try_inc()).count();
+    assert_eq!(rest, 3);
+    assert_eq!(child.saturating_reserve(1), 0);
+
+    // Unbounded sequencers get all of it.
+    assert_eq!(parent.saturating_reserve(1000), 1000);
+    assert!(parent.remaining() >= 1000);
+}
+
+#[test]
+fn batches_past_the_end_report_what_is_left() {
+    let (mut s, global) = private(16, Sequencer::NO_LAG);
+    global.store(Sequence::MAX - 100, Ordering::Relaxed);
+    s.inc();
+    // 15 left in the window, 84 at the global: a fresh window takes the larger.
+    assert_eq!(s.inc_n_checked(200), Err(OverflowError { available: 84 }));
+    assert_eq!(
+        global.head(),
+        Sequence::MAX - 84,
+        "a failed batch allocated"
+    );
+    assert_eq!(s.inc_n_checked(84), Ok(Sequence::MAX - 84));
+    assert_eq!(s.inc_n_checked(1), Err(OverflowError { available: 0 }));
+}
+
+#[test]
+fn sequences_map_to_their_step_window() {
+    let (s, _) = private(16, 64);
+    assert_eq!(s.window_of(0), 0..16);
+    assert_eq!(s.window_of(15), 0..16);
+    assert_eq!(s.window_of(16), 16..32);
+    assert_eq!(s.window_of(1000), 992..1008);
+    assert_eq!(
+        s.window_of(Sequence::MAX),
+        Sequence::MAX - 15..Sequence::MAX
+    );
+
+    let (s, _) = private(100, 100);
+    assert_eq!(s.window_of(250), 200..300);
+    let (s, _) = private(1, 1);
+    assert_eq!(s.window_of(7), 7..8);
+}
+
+#[test]
+fn more_cores_get_larger_steps() {
+    let step = |cores| Sequencer::auto_for(cores, Sequencer::NO_LAG).into_parts().2;
+    assert_eq!(step(1), 64);
+    assert!(step(64) > step(4), "{} <= {}", step(64), step(4));
+    assert_eq!(step(6), 512);
+    // Kept within bounds either way.
+    assert_eq!(step(0), 64);
+    assert_eq!(step(100_000), 8192);
+}
+
+#[test]
+#[cfg(feature = "stats")]
+fn lag_trips_raise_the_contention_estimate() {
+    let (mut s, global) = private(16, 16);
+    (0..100).for_each(|_| {
+        s.inc();
+    });
+    let quiet = s.contention_estimate();
+    assert_eq!(quiet, 0.0);
+
+    // Another allocator keeps running ahead by more than the lag.
+    for _ in 0..100 {
+        global.allocate(100);
+        s.inc();
+    }
+    let contended = s.contention_estimate();
+    assert!(contended > 0.5, "estimate {}", contended);
+    assert!(contended <= 1.0);
+}
+
+#[test]
+fn timestamps_never_go_back() {
+    let (mut s, _) = private(16, 64);
+    let mut last = s.inc_with_timestamp();
+    for i in 0..1000 {
+        if i % 100 == 0 {
+            coarsetime::Instant::update();
+        }
+        let next = s.inc_with_timestamp();
+        assert!(next.0 > last.0);
+        assert!(next.1 >= last.1, "{} after {}", next.1, last.1);
+        last = next;
+    }
+}
+
+#[test]
+fn planned_steps_keep_the_global_within_budget() {
+    for (threads, ops, budget) in [
+        (32, 10_000_000, 1_000_000),
+        (8, 1_000_000, 1_000_000),
+        (1, 1000, 1_000_000),
+        (64, 123_456_789, 999_999),
+    ] {
+        let (step, lag) = Sequencer::plan(threads, ops, budget);
+        let rate = threads as u64 * ops / step;
+        assert!(
+            rate <= budget,
+            "{} allocations per second over {}",
+            rate,
+            budget
+        );
+        // The smallest step that does.
+        assert!(step == 1 || threads as u64 * ops / (step - 1) > budget);
+        assert!(lag >= step);
+    }
+    assert_eq!(Sequencer::plan(32, 10_000_000, 1_000_000), (320, 320 * 16));
+}
+
+#[test]
+fn drained_windows_account_for_every_sequence() {
+    let (mut s, global) = private(16, 64);
+    (0..5).for_each(|_| {
+        s.inc();
+    });
+    let reserved = global.head();
+    let (consumed, returned) = s.drain_to_global();
+    assert_eq!((consumed, returned), (5, 11));
+    assert_eq!(consumed + returned, reserved);
+    assert_eq!(global.head(), 5);
+    assert_eq!(s.remaining(), 0);
+
+    // A window someone else allocated past can't go back.
+    s.inc();
+    global.allocate(16);
+    let (consumed, returned) = s.drain_to_global();
+    assert_eq!((consumed, returned), (1, 0));
+}
+
+#[test]
+fn broken_parts_fail_the_invariant_check() {
+    let check = |local, target, step, lag| {
+        Sequencer::from_parts(local, target, step, lag).check_invariants()
+    };
+    assert_eq!(check(10, 20, 16, 64), Ok(()));
+    // `reserve` and prefetching make windows larger than a step.
+    assert_eq!(check(0, 100, 16, 64), Ok(()));
+    assert_eq!(check(10, 20, 0, 64), Err("step is 0".to_string()));
+    assert_eq!(
+        check(10, 20, 16, 8),
+        Err("lag 8 is below step 16, every allocation would trip it".to_string())
+    );
+}
+
+#[test]
+fn compaction_closes_the_gaps_in_order() {
+    // Two threads' draws with window tails left out, interleaved as they were collected.
+    let first = [0, 1, 2, 64, 65, 200];
+    let second = [16, 17, 130, 131, 132];
+    let emitted = [&first[..], &second[..], &[2, 16][..]].concat();
+    let map = compact(&emitted);
+    assert_eq!(map.len(), 11);
+    let mut dense = map.values().copied().collect::<Vec<_>>();
+    dense.sort_unstable();
+    assert_eq!(dense, (0..11).collect::<Vec<_>>());
+    for thread in [&first[..], &second[..]] {
+        let mapped = thread.iter().map(|seq| map[seq]).collect::<Vec<_>>();
+        assert!(mapped.windows(2).all(|w| w[0] < w[1]), "{:?}", mapped);
+    }
+    assert_eq!(map[&16], 3);
+    assert!(compact(&[]).is_empty());
+}
+
+/// A backend counting what is asked of it, without `move_head`.
+#[derive(Default)]
+struct Counting {
+    head: AtomicSequence,
+    allocates: std::sync::atomic::AtomicUsize,
+}
+
+impl GlobalBackend for Counting {
+    fn allocate(&self, n: Sequence) -> Option<Sequence> {
+        self.allocates.fetch_add(1, Ordering::Relaxed);
+        self.head.allocate(n)
+    }
+
+    fn head(&self) -> Sequence {
+        self.head.head()
+    }
+}
+
+#[test]
+fn custom_backends_see_one_allocation_per_window() {
+    let backend = Arc::new(Counting::default());
+    let mut s = Sequencer::with_backend(backend.clone(), 16, 64);
+    let seqs = (0..100).map(|_| s.inc()).collect::<Vec<_>>();
+    assert_eq!(seqs, (0..100).collect::<Vec<_>>());
+    assert_eq!(backend.allocates.load(Ordering::Relaxed), 7);
+    // Without `move_head` the rest of the window is never handed back.
+    drop(s);
+    assert_eq!(backend.head(), 112);
+}
+
+#[test]
+fn buffers_are_filled_with_consecutive_sequences() {
+    let (mut s, _global) = private(16, Sequencer::NO_LAG);
+    let start = s.inc() + 1;
+    let mut buf = [0; 10];
+    s.inc_many_into(&mut buf);
+    assert_eq!(buf.to_vec(), (start..start + 10).collect::<Vec<_>>());
+
+    // Past the end of the window, and longer than a step.
+    for len in [10, 100] {
+        let mut buf = vec![0; len];
+        s.inc_many_into(&mut buf);
+        assert!(buf.windows(2).all(|w| w[1] == w[0] + 1), "{:?}", buf);
+    }
+    s.inc_many_into(&mut []);
+}
+
+#[test]
+fn paused_sequencers_serve_their_window_and_stop() {
+    let (mut s, global) = private(16, 32);
+    assert_eq!(s.inc(), 0);
+    s.pause();
+    assert!(s.is_paused());
+    // Far past the lag, which a paused sequencer has no other window to skip to.
+    global.store(1000, Ordering::Relaxed);
+    let seqs = std::iter::from_fn(|| s.try_inc()).collect::<Vec<_>>();
+    assert_eq!(seqs, (1..16).collect::<Vec<_>>());
+    assert!(!s.prefetch_next_window());
+    assert_eq!(s.try_inc(), None);
+    assert_eq!(global.head(), 1000);
+
+    s.resume();
+    assert!(!s.is_paused());
+    assert_eq!(s.try_inc(), Some(1000));
+    assert_eq!(global.head(), 1016);
+}
+
+#[test]
+#[should_panic(expected = "step must be at least 1")]
+fn a_step_of_0_is_rejected() {
+    private(0, Sequencer::NO_LAG);
+}
+
+#[test]
+fn boundaries_are_the_starts_of_reservations() {
+    let (mut s, _global) = private(4, Sequencer::NO_LAG);
+    let flags = |s: &mut Sequencer<_>, n| {
+        (0..n)
+            .filter_map(|_| match s.inc_with_boundary() {
+                (seq, true) => Some(seq),
+                (_, false) => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(flags(&mut s, 12), [0, 4, 8]);
+    // A batch spanning into a new window takes its start, and the window after it is
+    // reported again; one inside the window takes none.
+    s.inc();
+    assert_eq!(s.inc_n_checked(6), Ok(16));
+    assert_eq!(flags(&mut s, 4), [22]);
+    s.inc();
+    assert_eq!(s.inc_n_checked(2), Ok(27));
+    assert_eq!(flags(&mut s, 2), [30]);
+
+    // Taken back and handed out again, the start of the window isn't a new one.
+    assert_eq!(s.dec(), Some(30));
+    assert_eq!(flags(&mut s, 2), []);
+
+    // Neither the forked range nor what is left to the parent was reserved anew.
+    s.reserve(4);
+    let mut child = s.fork_range(2).unwrap();
+    assert_eq!(flags(&mut child, 2), []);
+    assert_eq!(flags(&mut s, 2), []);
+}
+
+#[test]
+fn prefetch_extensions_are_boundaries() {
+    let (mut s, _global) = private(16, Sequencer::NO_LAG);
+    assert_eq!(s.inc_with_boundary(), (0, true));
+    while s.remaining() > 2 {
+        assert!(!s.inc_with_boundary().1);
+    }
+    assert!(s.prefetch_next_window());
+    assert_eq!(s.inc_with_boundary(), (14, false));
+    assert_eq!(s.inc_with_boundary(), (15, false));
+    assert_eq!(s.inc_with_boundary(), (16, true));
+    assert_eq!(s.inc_with_boundary(), (17, false));
+}
+
+#[test]
+fn no_lag_sequencers_serve_their_window_whatever_the_global() {
+    let (mut s, global) = private(64, Sequencer::NO_LAG);
+    assert_eq!(draw(&mut s, 10), (0..10).collect::<Vec<_>>());
+    global.store(1 << 40, Ordering::Relaxed);
+    assert_eq!(draw(&mut s, 54), (10..64).collect::<Vec<_>>());
+    assert_eq!(global.head(), 1 << 40);
+    assert_eq!(s.inc(), 1 << 40);
+}
+
+#[test]
+fn fast_forwarding_is_the_lag_check_on_demand() {
+    let (mut s, global) = private(16, Sequencer::NO_LAG);
+    assert_eq!(draw(&mut s, 3), [0, 1, 2]);
+    // Within a step of `local`, a no-lag sequencer isn't behind.
+    global.store(3 + 16, Ordering::Relaxed);
+    s.fast_forward_if_behind();
+    assert_eq!(s.inc(), 3);
+
+    global.store(1000, Ordering::Relaxed);
+    // Plain `inc` never looks.
+    assert_eq!(s.inc(), 4);
+    assert_eq!(global.head(), 1000);
+    s.fast_forward_if_behind();
+    assert_eq!(global.head(), 1016);
+    assert_eq!(s.inc(), 1000);
+    // Caught up, it stays put.
+    s.fast_forward_if_behind();
+    assert_eq!(s.inc(), 1001);
+    assert_eq!(global.head(), 1016);
+}
+
+#[test]
+fn prefetching_extends_the_window_in_place() {
+    let (mut s, global) = private(16, Sequencer::NO_LAG);
+    draw(&mut s, 8);
+    // Too much of the window left still.
+    assert!(!s.prefetch_next_window());
+    draw(&mut s, 6);
+    assert_eq!(s.local_window(), 14..16);
+    assert!(s.prefetch_next_window());
+    assert_eq!(s.local_window(), 14..32);
+    assert_eq!(global.head(), 32);
+    assert_eq!(draw(&mut s, 18), (14..32).collect::<Vec<_>>());
+
+    // Once another sequencer took the space after the window, it can't grow into it.
+    let mut other = Sequencer::with_backend(global.clone(), 16, Sequencer::NO_LAG);
+    draw(&mut s, 15);
+    assert_eq!(other.inc(), 48);
+    assert!(!s.prefetch_next_window());
+    assert_eq!(s.local_window(), 47..48);
+    assert_eq!(global.head(), 64);
+}
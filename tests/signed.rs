@@ -0,0 +1,29 @@
+use rust_benchmark_atomic::signed::{signed_global, SignedSequencer};
+
+#[test]
+fn sequences_cross_zero_in_order() {
+    let global = signed_global(-100);
+    let mut a = SignedSequencer::new(global.clone(), 16, 64);
+    let mut b = SignedSequencer::new(global, 16, 64);
+    let mut seqs = vec![];
+    for _ in 0..10 {
+        let from_a = (0..10).map(|_| a.inc()).collect::<Vec<_>>();
+        assert!(from_a.windows(2).all(|w| w[0] < w[1]));
+        seqs.extend(from_a);
+        seqs.push(b.inc());
+    }
+    assert_eq!(seqs.iter().min(), Some(&-100));
+    assert!(seqs.iter().any(|&seq| seq > 0));
+    seqs.sort_unstable();
+    let len = seqs.len();
+    seqs.dedup();
+    assert_eq!(seqs.len(), len, "duplicate sequences");
+}
+
+#[test]
+fn the_last_signed_sequence_is_below_i64_max() {
+    let mut s = SignedSequencer::new(signed_global(i64::MAX - 16), 16, 64);
+    let seqs = std::iter::from_fn(|| s.try_inc()).collect::<Vec<_>>();
+    assert_eq!(seqs, (i64::MAX - 16..i64::MAX).collect::<Vec<_>>());
+    assert_eq!(s.remaining_global_space(), 0);
+}
@@ -0,0 +1,98 @@
+//! The thread-local sequencers never hand out a sequence twice, however their windows
+//! interleave. `UNIQUENESS_LOOPS` sets the incs per thread, a million by default; lower it
+//! for quick runs.
+
+use std::sync::atomic::Ordering;
+
+use rust_benchmark_atomic::sequence::{Sequence, Sequencer, SEQUENCER, SEQUENCE_GLOBAL};
+
+const THREADS: usize = 8;
+
+#[cfg(feature = "stats")]
+fn lag_trips() -> u64 {
+    SEQUENCER.with(|s| s.borrow().stats().lag_trips)
+}
+
+#[cfg(not(feature = "stats"))]
+fn lag_trips() -> u64 {
+    0
+}
+
+#[test]
+fn thread_local_sequencers_never_collide() {
+    let loops = std::env::var("UNIQUENESS_LOOPS")
+        .ok()
+        .map_or(1_000_000, |n| n.parse().expect("UNIQUENESS_LOOPS"));
+    SEQUENCE_GLOBAL.store(0, Ordering::Relaxed);
+
+    let per_thread = (0..THREADS)
+        .map(|_| {
+            std::thread::spawn(move || {
+                let seqs = (0..loops)
+                    .map(|_| SEQUENCER.with(|s| s.borrow_mut().inc()))
+                    .collect::<Vec<_>>();
+                (seqs, lag_trips())
+            })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    let (per_thread, lag_trips): (Vec<_>, Vec<_>) = per_thread.into_iter().unzip();
+    for seqs in &per_thread {
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]), "a thread went back");
+    }
+
+    let mut all = per_thread.concat();
+    let total = all.len();
+    all.sort_unstable();
+    all.dedup();
+    assert_eq!(all.len(), total, "duplicate sequences");
+
+    // Whatever the span holds beyond the sequences drawn are window tails left unused, by
+    // lag trips or by threads that stopped; all of it was allocated from the global.
+    let head = SEQUENCE_GLOBAL.load(Ordering::Relaxed);
+    let (min, max) = (all[0], all[total - 1]);
+    let span = max - min + 1;
+    assert!(
+        span >= total as Sequence,
+        "span {} below {} sequences",
+        span,
+        total
+    );
+    assert!(
+        max < head,
+        "sequence {} not below the global head {}",
+        max,
+        head
+    );
+
+    // Each tail is less than a step. A thread only leaves a tail behind where it jumps to
+    // a new window, or at the end of its run, so whole windows can't go to waste.
+    const STEP: Sequence = Sequencer::DEFAULT_STEP;
+    let gap = head - total as Sequence;
+    let jumps = per_thread
+        .iter()
+        .map(|seqs| seqs.windows(2).filter(|w| w[1] != w[0] + 1).count())
+        .sum::<usize>();
+    let bound = (jumps + THREADS) as Sequence * STEP;
+    eprintln!(
+        "{} sequences, {} left in window tails, {} window jumps",
+        total, gap, jumps
+    );
+    assert!(gap <= bound, "{} left in tails, above {}", gap, bound);
+    #[cfg(feature = "stats")]
+    {
+        // Tails are left behind by lag trips then, the other jumps are to full windows.
+        let trips = lag_trips.iter().sum::<u64>();
+        let bound = (trips + THREADS as u64) * STEP;
+        assert!(
+            gap <= bound,
+            "{} left in tails, above {} after {} lag trips",
+            gap,
+            bound,
+            trips
+        );
+    }
+}
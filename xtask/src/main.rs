@@ -0,0 +1,78 @@
+//! Maintenance tasks, run as `cargo xtask <task>` from anywhere in the repo.
+//!
+//! `check-features`: checks every target of the crate with no features, with each feature
+//! alone and with all of them, to catch a `cfg` guard missing from some combination.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+fn main() -> ExitCode {
+    match std::env::args().nth(1).as_deref() {
+        Some("check-features") => check_features(),
+        _ => {
+            eprintln!("usage: cargo xtask check-features");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// The names in the `[features]` table of the crate's manifest.
+fn features(manifest: &str) -> Vec<String> {
+    manifest
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "[features]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            line.split_once('=')
+                .map(|(name, _)| name.trim().to_string())
+        })
+        .collect()
+}
+
+fn check_features() -> ExitCode {
+    let root = root();
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).expect("read Cargo.toml");
+    let features = features(&manifest);
+
+    let mut combos = vec![String::new()];
+    combos.extend(features.iter().cloned());
+    combos.push(features.join(","));
+
+    let mut failed = vec![];
+    for combo in &combos {
+        let label = if combo.is_empty() { "(none)" } else { combo };
+        eprintln!("checking features: {}", label);
+        let status = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+            .current_dir(&root)
+            // Unused imports and dead code under one combination are `cfg` mistakes too.
+            .env("RUSTFLAGS", "-D warnings")
+            .args(["check", "--quiet", "--all-targets", "--no-default-features"])
+            .args(["--features", combo])
+            .status()
+            .expect("run cargo");
+        if !status.success() {
+            failed.push(label.to_string());
+        }
+    }
+
+    if failed.is_empty() {
+        eprintln!("all {} feature combinations build", combos.len());
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("failed: {}", failed.join("; "));
+        ExitCode::FAILURE
+    }
+}